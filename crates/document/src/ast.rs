@@ -3,9 +3,23 @@ use std::fmt::{write, Write};
 
 #[derive(Debug)]
 pub enum AST {
-    Container { kind: String, children: Vec<AST> },
-    Fixed { kind: String, reference: String },
-    Referenced { reference: String },
+    Container {
+        kind: String,
+        children: Vec<AST>,
+    },
+    Fixed {
+        kind: String,
+        reference: String,
+    },
+    Referenced {
+        reference: String,
+    },
+    /// A node that is also handed to a placeholder as a whole,
+    /// e.g. a block containing `$(iam_no_wildcard_admin)`
+    Captured {
+        node: Box<AST>,
+        reference: String,
+    },
     Any,
 }
 
@@ -21,7 +35,7 @@ impl ToSexp for AST {
                 })?;
                 write!(output, ")")?;
 
-                if kind == "resource" {
+                if terraform::is_top_level_block(kind) {
                     write!(output, " @result")
                 } else {
                     Result::Ok(())
@@ -32,6 +46,10 @@ impl ToSexp for AST {
                 format_args!("({kind}) @{reference}", kind = kind, reference = r),
             ),
             AST::Referenced { reference } => write(output, format_args!("(*) @{}", reference)),
+            AST::Captured { node, reference } => {
+                node.to_sexp(output)?;
+                write(output, format_args!(" @{}", reference))
+            }
         }
     }
 }
@@ -68,6 +86,24 @@ mod tests {
         )
     }
 
+    #[test]
+    fn every_top_level_block_gets_a_reference_named_result() {
+        matches_sexp(
+            AST::Container {
+                kind: "data".into(),
+                children: vec![AST::Any],
+            },
+            "(data (*) ) @result",
+        );
+        matches_sexp(
+            AST::Container {
+                kind: "block".into(),
+                children: vec![AST::Any],
+            },
+            "(block (*) )",
+        )
+    }
+
     #[test]
     fn nodes_with_a_fixed_value_use_reference_to_match_later() {
         matches_sexp(
@@ -89,6 +125,20 @@ mod tests {
         )
     }
 
+    #[test]
+    fn captured_nodes_get_an_additional_reference() {
+        matches_sexp(
+            AST::Captured {
+                node: Box::new(AST::Container {
+                    kind: "block".into(),
+                    children: vec![],
+                }),
+                reference: "1".into(),
+            },
+            "(block ) @1",
+        )
+    }
+
     fn matches_sexp<T: ToSexp>(node: T, sexp: &'static str) {
         let mut buffer = String::new();
         node.to_sexp(&mut buffer)
@@ -0,0 +1,263 @@
+use super::rule::{Decision, MatchResult, NodeInfo};
+use super::Document;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use terraform::address::Address;
+
+/// What to do when one document allows a block another one denies
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Precedence {
+    /// Leave both verdicts and report that they disagree
+    Report,
+    DenyOverrides,
+    AllowOverrides,
+    /// The documents with the highest `priority:` in their front matter win,
+    /// documents without one have a priority of 0
+    Priority,
+}
+
+impl FromStr for Precedence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "report" => Ok(Precedence::Report),
+            "deny-overrides" => Ok(Precedence::DenyOverrides),
+            "allow-overrides" => Ok(Precedence::AllowOverrides),
+            "priority" => Ok(Precedence::Priority),
+            other => Err(format!(
+                "`{}` is not one of report, deny-overrides, allow-overrides or priority",
+                other
+            )),
+        }
+    }
+}
+
+/// A block some documents allow and others deny, that could not be resolved
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub node_info: NodeInfo,
+    pub address: Option<Address>,
+    /// The titles of the documents on either side
+    pub allowed_by: Vec<String>,
+    pub denied_by: Vec<String>,
+}
+
+/// Settles the blocks the documents disagree on, by dropping the results of the
+/// documents that lost. `results` holds what each of `documents` matched, in the same order.
+pub fn resolve(
+    documents: &[Document],
+    results: &mut [Vec<MatchResult>],
+    precedence: Precedence,
+) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+
+    for (node, verdicts) in verdicts(results) {
+        let allowed: Vec<usize> = verdicts
+            .iter()
+            .filter(|(_, decision)| *decision == Decision::Allow)
+            .map(|(idx, _)| *idx)
+            .collect();
+        let denied: Vec<usize> = verdicts
+            .iter()
+            .filter(|(_, decision)| *decision == Decision::Deny)
+            .map(|(idx, _)| *idx)
+            .collect();
+        if allowed.is_empty() || denied.is_empty() {
+            continue;
+        }
+
+        let losers = match precedence {
+            Precedence::Report => Vec::new(),
+            Precedence::DenyOverrides => allowed.clone(),
+            Precedence::AllowOverrides => denied.clone(),
+            Precedence::Priority => {
+                let highest = |side: &[usize]| {
+                    side.iter()
+                        .map(|idx| documents[*idx].priority)
+                        .max()
+                        .unwrap_or_default()
+                };
+                let (allow, deny) = (highest(&allowed), highest(&denied));
+                if allow > deny {
+                    denied.clone()
+                } else if deny > allow {
+                    allowed.clone()
+                } else {
+                    Vec::new()
+                }
+            }
+        };
+
+        if losers.is_empty() {
+            let result = results[allowed[0]]
+                .iter()
+                .find(|m| m.node_info.id == node)
+                .expect("a verdict comes from a result");
+            let titles = |side: &[usize]| {
+                side.iter()
+                    .map(|idx| documents[*idx].title.clone())
+                    .collect()
+            };
+            conflicts.push(Conflict {
+                node_info: result.node_info.clone(),
+                address: result.address.clone(),
+                allowed_by: titles(&allowed),
+                denied_by: titles(&denied),
+            });
+        }
+
+        for idx in losers {
+            results[idx].retain(|m| m.node_info.id != node);
+        }
+    }
+
+    conflicts
+}
+
+/// Within a document an `Allow` beats a `Deny`, which beats a `Warn`, which beats an `Info`
+fn outweighs(decision: Decision, other: Decision) -> bool {
+    let weight = |decision| match decision {
+        Decision::Allow => 3,
+        Decision::Deny => 2,
+        Decision::Warn => 1,
+        Decision::Info | Decision::Require => 0,
+    };
+    weight(decision) > weight(other)
+}
+
+/// For every block, what each document that matched it decided about it.
+/// A document allows a block when any of its `Allow` rules match, like the report does.
+fn verdicts(results: &[Vec<MatchResult>]) -> BTreeMap<usize, Vec<(usize, Decision)>> {
+    let mut verdicts: BTreeMap<usize, Vec<(usize, Decision)>> = BTreeMap::new();
+
+    for (idx, matches) in results.iter().enumerate() {
+        for m in matches {
+            let document_verdicts = verdicts.entry(m.node_info.id).or_default();
+            match document_verdicts.iter_mut().find(|(doc, _)| *doc == idx) {
+                Some((_, decision)) if outweighs(m.decision, *decision) => *decision = m.decision,
+                Some(_) => {}
+                None => document_verdicts.push((idx, m.decision)),
+            }
+        }
+    }
+
+    verdicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_path;
+    use pretty_assertions::assert_eq;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn documents(markdowns: &[&str]) -> Vec<Document> {
+        let dir = tempdir().unwrap();
+        markdowns
+            .iter()
+            .enumerate()
+            .map(|(idx, markdown)| {
+                let file_path = dir.path().join(format!("{}.md", idx));
+                let mut file = File::create(&file_path).unwrap();
+                write!(file, "{}", markdown).unwrap();
+                from_path(file_path).expect("there should have been a doc")
+            })
+            .collect()
+    }
+
+    static ALLOW_MYSQL: &str = r#"---
+priority: 10
+---
+# Databases team
+
+## Allow: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+
+## Deny: Other engines
+
+```
+resource "aws_db_instance" $(*) {
+}
+```
+"#;
+
+    static DENY_MYSQL: &str = r#"
+# Security team
+
+## Deny: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+"#;
+
+    fn resolved(precedence: Precedence) -> (Vec<Vec<Decision>>, Vec<Conflict>) {
+        let documents = documents(&[ALLOW_MYSQL, DENY_MYSQL]);
+        let terraform = terraform::parse_text(
+            r#"
+resource "aws_db_instance" "main" {
+  engine = "mysql"
+}
+"#,
+        );
+        let mut results: Vec<Vec<MatchResult>> = documents
+            .iter()
+            .map(|doc| doc.matches(&terraform))
+            .collect();
+
+        let conflicts = resolve(&documents, &mut results, precedence);
+        let decisions = results
+            .iter()
+            .map(|matches| matches.iter().map(|m| m.decision).collect())
+            .collect();
+        (decisions, conflicts)
+    }
+
+    #[test]
+    fn reports_documents_that_disagree() {
+        let (decisions, conflicts) = resolved(Precedence::Report);
+
+        assert_eq!(
+            vec![vec![Decision::Allow, Decision::Deny], vec![Decision::Deny]],
+            decisions
+        );
+        assert_eq!(1, conflicts.len());
+        assert_eq!(vec!["Databases team".to_string()], conflicts[0].allowed_by);
+        assert_eq!(vec!["Security team".to_string()], conflicts[0].denied_by);
+    }
+
+    #[test]
+    fn drops_the_verdicts_that_lost() {
+        let (decisions, conflicts) = resolved(Precedence::DenyOverrides);
+        assert_eq!(vec![vec![], vec![Decision::Deny]], decisions);
+        assert!(conflicts.is_empty());
+
+        let (decisions, _) = resolved(Precedence::AllowOverrides);
+        assert_eq!(
+            vec![vec![Decision::Allow, Decision::Deny], vec![]],
+            decisions
+        );
+
+        let (decisions, _) = resolved(Precedence::Priority);
+        assert_eq!(
+            vec![vec![Decision::Allow, Decision::Deny], vec![]],
+            decisions
+        );
+    }
+
+    #[test]
+    fn reads_precedences_from_the_command_line() {
+        assert_eq!(Ok(Precedence::DenyOverrides), "deny-overrides".parse());
+        assert!("first-wins".parse::<Precedence>().is_err());
+    }
+}
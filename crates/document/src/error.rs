@@ -0,0 +1,97 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can be wrong with a rule document
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// The markdown is missing one of the parts of a rule document
+    Malformed {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    /// A code block that could not be turned into a rule.
+    /// `line` and `column` are where the code block starts in the markdown.
+    Rule {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        title: String,
+        reason: String,
+        /// The line inside of the code block, starting at 1
+        snippet_line: Option<usize>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => {
+                write!(f, "{}: could not be read: {}", path.display(), source)
+            }
+            Error::Malformed {
+                path,
+                line,
+                column,
+                reason,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, reason),
+            Error::Rule {
+                path,
+                line,
+                column,
+                title,
+                reason,
+                snippet_line,
+            } => {
+                write!(
+                    f,
+                    "{}:{}:{}: rule \"{}\": {}",
+                    path.display(),
+                    line,
+                    column,
+                    title,
+                    reason
+                )?;
+                match snippet_line {
+                    Some(snippet_line) => write!(f, " (line {} of the snippet)", snippet_line),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Something in a rule document that is likely a mistake, but doesn't stop it from being used
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+/// The `key: value` lines between two `---` at the very top of a document
+#[derive(Debug, Default, PartialEq)]
+pub struct FrontMatter {
+    entries: BTreeMap<String, String>,
+}
+
+impl FrontMatter {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// The comma separated values of a key, e.g. `matching: case-insensitive, trim-whitespace`
+    pub fn list(&self, key: &str) -> Vec<&str> {
+        self.get(key)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Separates the front matter from the markdown that follows it.
+/// Documents written on Windows use `\r\n` for their separators as well.
+pub fn split(content: &str) -> (FrontMatter, &str) {
+    let (body, separator) = match content.strip_prefix("---\n") {
+        Some(body) => (body, "\n---\n"),
+        None => match content.strip_prefix("---\r\n") {
+            Some(body) => (body, "\r\n---\r\n"),
+            None => return (FrontMatter::default(), content),
+        },
+    };
+
+    let end = match body.find(separator) {
+        Some(end) => end,
+        None => return (FrontMatter::default(), content),
+    };
+
+    let entries = body[..end]
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+
+    (FrontMatter { entries }, &body[end + separator.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn reads_key_values_until_the_second_separator() {
+        let (front_matter, rest) =
+            split("---\nnr: 1235\nmatching: case-insensitive, trim-whitespace\n---\n# Title\n");
+
+        assert_eq!(Some("1235"), front_matter.get("nr"));
+        assert_eq!(
+            vec!["case-insensitive", "trim-whitespace"],
+            front_matter.list("matching")
+        );
+        assert_eq!("# Title\n", rest);
+    }
+
+    #[test]
+    fn reads_front_matter_with_windows_line_endings() {
+        let (front_matter, rest) = split("---\r\nnr: 1235\r\npriority: 10\r\n---\r\n# Title\r\n");
+
+        assert_eq!(Some("1235"), front_matter.get("nr"));
+        assert_eq!(Some("10"), front_matter.get("priority"));
+        assert_eq!("# Title\r\n", rest);
+    }
+
+    #[test]
+    fn documents_without_front_matter_are_left_alone() {
+        let (front_matter, rest) = split("# Title\n---\n");
+
+        assert_eq!(FrontMatter::default(), front_matter);
+        assert_eq!("# Title\n---\n", rest);
+    }
+}
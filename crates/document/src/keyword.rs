@@ -0,0 +1,153 @@
+use super::rule::Decision;
+use std::str::FromStr;
+
+/// The words a `##` heading can start with to make the code blocks below it a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keywords {
+    words: Vec<(String, Decision)>,
+}
+
+impl Default for Keywords {
+    fn default() -> Self {
+        Keywords {
+            words: vec![
+                ("Allow".into(), Decision::Allow),
+                ("Deny".into(), Decision::Deny),
+                ("Warn".into(), Decision::Warn),
+                ("Info".into(), Decision::Info),
+                ("Require".into(), Decision::Require),
+            ],
+        }
+    }
+}
+
+/// Another word for a decision, e.g. `Forbid=Deny` for a project that writes its rules that way
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alias {
+    word: String,
+    decision: Decision,
+}
+
+impl FromStr for Alias {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let word = parts.next().unwrap_or_default().trim();
+        let decision = match parts.next().map(str::trim) {
+            Some("Allow") => Decision::Allow,
+            Some("Deny") => Decision::Deny,
+            Some("Warn") => Decision::Warn,
+            Some("Info") => Decision::Info,
+            Some("Require") => Decision::Require,
+            _ => {
+                return Err(format!(
+                    "`{}` should look like `Word=Allow`, with Allow, Deny, Warn, Info or Require",
+                    s
+                ))
+            }
+        };
+        if word.is_empty() || !word.chars().all(char::is_alphanumeric) {
+            return Err(format!("`{}` can only be used for a single word", word));
+        }
+
+        Ok(Alias {
+            word: word.into(),
+            decision,
+        })
+    }
+}
+
+/// What a heading says about the code blocks below it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Heading {
+    Decision(Decision),
+    /// Headings like `## Rationale` that aren't about a rule
+    Other,
+    /// Headings like `## allow MySQL` or `## DENY: everything` that look like
+    /// they were meant to be a rule, but don't start with a keyword as it is written
+    Ambiguous,
+}
+
+impl Keywords {
+    pub fn with_aliases(aliases: &[Alias]) -> Self {
+        let mut keywords = Keywords::default();
+        keywords.words.extend(
+            aliases
+                .iter()
+                .map(|alias| (alias.word.clone(), alias.decision)),
+        );
+        keywords
+    }
+
+    /// Every keyword, for telling people what a heading could start with
+    pub fn words(&self) -> Vec<&str> {
+        self.words.iter().map(|(word, _)| word.as_str()).collect()
+    }
+
+    /// Only the first word of a heading counts, so `## Deny - any other DB engine` is a `Deny`
+    pub(crate) fn heading(&self, title: &str) -> Heading {
+        let first = title
+            .split(|c: char| !c.is_alphanumeric())
+            .find(|word| !word.is_empty())
+            .unwrap_or_default();
+
+        if let Some((_, decision)) = self.words.iter().find(|(word, _)| word == first) {
+            Heading::Decision(*decision)
+        } else if self
+            .words
+            .iter()
+            .any(|(word, _)| word.to_lowercase() == first.to_lowercase())
+        {
+            Heading::Ambiguous
+        } else {
+            Heading::Other
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn the_first_word_of_a_heading_decides() {
+        let keywords = Keywords::default();
+
+        assert_eq!(
+            Heading::Decision(Decision::Allow),
+            keywords.heading("Allow: MySQL")
+        );
+        assert_eq!(
+            Heading::Decision(Decision::Deny),
+            keywords.heading("Deny - any other DB engine")
+        );
+        assert_eq!(Heading::Decision(Decision::Warn), keywords.heading("Warn"));
+        assert_eq!(
+            Heading::Decision(Decision::Info),
+            keywords.heading("Info: old instance types")
+        );
+        assert_eq!(Heading::Other, keywords.heading("Rationale"));
+        assert_eq!(Heading::Ambiguous, keywords.heading("allow MySQL"));
+        assert_eq!(Heading::Ambiguous, keywords.heading("DENY: everything"));
+        assert_eq!(Heading::Other, keywords.heading("MySQL is allowed"));
+        assert_eq!(Heading::Other, keywords.heading("Allowed engines"));
+        assert_eq!(Heading::Other, keywords.heading("Requirements"));
+        assert_eq!(Heading::Other, keywords.heading("Further information"));
+        assert_eq!(Heading::Other, keywords.heading("Warning signs"));
+        assert_eq!(Heading::Other, keywords.heading("Denial of service"));
+    }
+
+    #[test]
+    fn aliases_add_keywords() {
+        let keywords = Keywords::with_aliases(&["Forbid=Deny".parse().unwrap()]);
+
+        assert_eq!(
+            Heading::Decision(Decision::Deny),
+            keywords.heading("Forbid: MySQL")
+        );
+        assert!("Forbid".parse::<Alias>().is_err());
+        assert!("Do not=Deny".parse::<Alias>().is_err());
+    }
+}
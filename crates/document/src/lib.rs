@@ -1,90 +1,255 @@
+use front_matter::FrontMatter;
+use keyword::Keywords;
 use pulldown_cmark::{
+    CodeBlockKind,
     Event::{Start, Text},
-    Parser,
+    OffsetIter, Parser,
     Tag::{CodeBlock, Heading},
 };
-use rule::{Decision, MatchResult, Rule};
+use rule::{Decision, MatchOptions, MatchResult, Rule};
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
 
 use terraform::BackingData;
 
+pub use error::{Error, Warning};
+
 #[macro_use]
 extern crate lazy_static;
 
 mod ast;
+pub mod conflict;
+mod error;
+pub mod front_matter;
+pub mod keyword;
+pub mod lint;
+mod operation;
 pub mod rule;
+mod structured;
+mod template;
 
 #[derive(Debug)]
 pub struct Document {
     path: PathBuf,
     title: String,
+    options: MatchOptions,
+    /// `priority: 10` in the front matter, for settling conflicts between documents
+    priority: i64,
     pub rules: Vec<Rule>,
+    /// Headings that look like they should have been a rule, but aren't one
+    pub warnings: Vec<Warning>,
 }
 
 impl Document {
     pub fn matches(&self, terraform: &BackingData) -> Vec<MatchResult> {
         self.rules
             .iter()
+            .filter(|r| r.decision != Decision::Require)
             .flat_map(|r| r.matches(terraform))
             .collect()
     }
+
+    /// The `Require` rules none of the files of a root module match
+    pub fn missing(&self, module: &[&BackingData]) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|r| r.decision == Decision::Require)
+            .filter(|r| {
+                module
+                    .iter()
+                    .all(|terraform| r.matches(terraform).is_empty())
+            })
+            .collect()
+    }
 }
 
-pub fn from_path(path: PathBuf) -> Option<Document> {
-    let mut file = File::open(path.clone()).expect("could not open rule file");
+/// What the code blocks below a `##` heading are for
+enum Section {
+    Rule(String, Decision),
+    /// A rule whose code block has been read already
+    Complete,
+    Other,
+}
+
+pub fn from_path(path: PathBuf) -> Result<Document, Error> {
+    from_path_with(path, &Keywords::default())
+}
+
+/// Reads a document whose headings may use the words of a project as well, e.g. `## Forbid`
+pub fn from_path_with(path: PathBuf, keywords: &Keywords) -> Result<Document, Error> {
     let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .expect("was not able to read input");
+    File::open(&path)
+        .and_then(|mut file| file.read_to_end(&mut buffer))
+        .map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        })?;
 
-    let content = std::str::from_utf8(&buffer[..]).unwrap();
+    let file = std::str::from_utf8(&buffer[..]).map_err(|e| {
+        let valid = std::str::from_utf8(&buffer[..e.valid_up_to()]).unwrap_or_default();
+        let (line, column) = position(valid, valid.len());
+        Error::Malformed {
+            path: path.clone(),
+            line,
+            column,
+            reason: "is not valid UTF-8".into(),
+        }
+    })?;
+    let (front_matter, content) = front_matter::split(file);
+    // offsets of the markdown parser start after the front matter
+    let skipped = file.len() - content.len();
+
+    let mut parser = Parser::new(&content).into_offset_iter();
 
-    let mut parser = Parser::new(&content);
+    let priority = match front_matter.get("priority") {
+        Some(priority) => priority.parse().map_err(|_| {
+            let (line, column) = position(file, file.find("priority:").unwrap_or_default());
+            Error::Malformed {
+                path: path.clone(),
+                line,
+                column,
+                reason: "the priority has to be a whole number".into(),
+            }
+        })?,
+        None => 0,
+    };
 
     let mut doc = Document {
         path,
         title: "".into(),
+        options: match_options(&front_matter),
+        priority,
         rules: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let malformed = |path: &PathBuf, offset: usize, reason: &str| {
+        let (line, column) = position(file, skipped + offset);
+        Error::Malformed {
+            path: path.clone(),
+            line,
+            column,
+            reason: reason.into(),
+        }
     };
 
     let mut current_rule = None;
 
-    while let Some(event) = parser.next() {
+    while let Some((event, range)) = parser.next() {
         match event {
             Start(Heading(1)) => {
-                doc.title = consume_text(&mut parser).expect("there should have been a doc title");
+                doc.title = consume_text(&mut parser).ok_or_else(|| {
+                    malformed(
+                        &doc.path,
+                        range.start,
+                        "the title of the document has no text",
+                    )
+                })?;
             }
             Start(Heading(2)) => {
-                let title = consume_text(&mut parser).expect("there should have been title text");
+                let title = consume_text(&mut parser).ok_or_else(|| {
+                    malformed(&doc.path, range.start, "a rule has a heading without text")
+                })?;
 
-                let decision = if title.starts_with("Allow") {
-                    Decision::Allow
-                } else {
-                    Decision::Deny
+                current_rule = match keywords.heading(&title) {
+                    keyword::Heading::Decision(decision) => Some(Section::Rule(title, decision)),
+                    keyword::Heading::Other => Some(Section::Other),
+                    keyword::Heading::Ambiguous => {
+                        let (line, column) = position(file, skipped + range.start);
+                        doc.warnings.push(Warning {
+                            path: doc.path.clone(),
+                            line,
+                            column,
+                            message: format!(
+                                "`## {}` is not a rule, as it doesn't start with one of {}",
+                                title,
+                                keywords.words().join(", ")
+                            ),
+                        });
+                        Some(Section::Other)
+                    }
                 };
-                current_rule = Some((title, decision));
             }
-            Start(CodeBlock(_)) => {
-                if let Some((title, decision)) = current_rule {
-                    let code = consume_text(&mut parser).expect("there was no code");
-                    let rule = Rule::new(title, decision, code).expect(
-                        "TODO: if this fail I should collect the error and report it later?",
-                    );
-                    doc.rules.push(rule);
-                }
-
-                current_rule = None
+            Start(CodeBlock(kind)) if is_terraform(&kind) => {
+                let (title, decision) =
+                    match current_rule.take() {
+                        Some(Section::Rule(title, decision)) => {
+                            current_rule = Some(Section::Complete);
+                            (title, decision)
+                        }
+                        // code blocks under other headings are examples, not rules
+                        Some(Section::Other) => {
+                            current_rule = Some(Section::Other);
+                            continue;
+                        }
+                        Some(Section::Complete) => return Err(malformed(
+                            &doc.path,
+                            range.start,
+                            "only one code block per rule heading, give the other one its own `##` heading",
+                        )),
+                        None => return Err(malformed(
+                            &doc.path,
+                            range.start,
+                            "a code block has to follow a `##` heading saying what to do with it",
+                        )),
+                    };
+
+                // an empty code block has no text at all
+                let code = consume_text(&mut parser).unwrap_or_default();
+                let mut rule = Rule::new(title.clone(), decision, code).map_err(|error| {
+                    let (line, column) = position(file, skipped + range.start);
+                    Error::Rule {
+                        path: doc.path.clone(),
+                        line,
+                        column,
+                        title,
+                        reason: error.reason,
+                        snippet_line: error.line,
+                    }
+                })?;
+                rule.options = doc.options;
+                doc.rules.push(rule);
             }
             _ => {}
         }
     }
 
-    Some(doc)
+    Ok(doc)
+}
+
+/// Code blocks marked as anything other than `terraform` or `hcl` are there for people to read
+fn is_terraform(kind: &CodeBlockKind) -> bool {
+    match kind {
+        CodeBlockKind::Indented => true,
+        CodeBlockKind::Fenced(info) => matches!(
+            info.split_whitespace().next(),
+            None | Some("terraform") | Some("hcl")
+        ),
+    }
+}
+
+/// The line and column of a byte offset, both starting at 1
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|idx| idx + 1).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// `matching: case-insensitive, trim-whitespace` in the front matter
+/// applies to every rule of the document
+fn match_options(front_matter: &FrontMatter) -> MatchOptions {
+    let matching = front_matter.list("matching");
+
+    MatchOptions {
+        case_insensitive: matching.contains(&"case-insensitive"),
+        trim_whitespace: matching.contains(&"trim-whitespace"),
+    }
 }
 
-fn consume_text(p: &mut Parser) -> Option<String> {
-    if let Some(Text(t)) = p.next() {
+fn consume_text(p: &mut OffsetIter) -> Option<String> {
+    if let Some((Text(t), _)) = p.next() {
         return Some(t.into_string());
     }
     None
@@ -137,4 +302,271 @@ resource "aws_db_instance" $(*) {
         assert_eq!(doc.rules[0].decision, Decision::Allow);
         assert_eq!(doc.rules[1].decision, Decision::Deny);
     }
+
+    #[test]
+    fn rule_files_that_cant_be_read_are_errors() {
+        let dir = tempdir().unwrap();
+
+        match from_path(dir.path().join("missing.md")) {
+            Err(Error::Io { path, .. }) => assert_eq!(dir.path().join("missing.md"), path),
+            other => panic!("expected an io error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn empty_code_blocks_are_errors() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+
+        write!(
+            file,
+            "{}",
+            r#"
+# Nothing to see
+
+## Deny: Everything
+
+```
+```
+"#
+        )
+        .unwrap();
+
+        match from_path(file_path) {
+            Err(Error::Rule { title, .. }) => assert_eq!("Deny: Everything", title),
+            other => panic!("expected a rule error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rule_errors_point_into_the_markdown() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+
+        write!(
+            file,
+            "{}",
+            r#"---
+matching: case-insensitive
+---
+# Old engines
+
+## Deny: Unknown checks
+
+```
+resource "aws_db_instance" $(*) {
+  engine = $(not_a_check)
+}
+```
+"#
+        )
+        .unwrap();
+
+        match from_path(file_path) {
+            Err(Error::Rule {
+                line,
+                column,
+                snippet_line,
+                ..
+            }) => assert_eq!((8, 1, Some(2)), (line, column, snippet_line)),
+            other => panic!("expected a rule error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn code_blocks_need_a_heading() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+
+        write!(
+            file,
+            "{}",
+            r#"# No heading
+
+```
+resource "aws_db_instance" $(*) {
+}
+```
+"#
+        )
+        .unwrap();
+
+        match from_path(file_path) {
+            Err(Error::Malformed { line, .. }) => assert_eq!(3, line),
+            other => panic!("expected a malformed document, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rules_have_a_single_code_block() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+
+        write!(
+            file,
+            "{}",
+            r#"# Databases
+
+## Deny: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+
+```
+resource "aws_rds_cluster" $(*) {
+  engine = "aurora-mysql"
+}
+```
+"#
+        )
+        .unwrap();
+
+        match from_path(file_path) {
+            Err(Error::Malformed { line, reason, .. }) => {
+                assert_eq!(11, line);
+                assert!(reason.starts_with("only one code block per rule heading"));
+            }
+            other => panic!("expected a malformed document, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn only_decision_headings_and_terraform_code_blocks_become_rules() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+
+        write!(
+            file,
+            "{}",
+            r#"# Only allow MySQL
+
+## Rationale
+
+```
+resource "aws_db_instance" "example" {
+  engine = "mysql"
+}
+```
+
+## allow: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+
+## Warn: Other engines
+
+```json
+{ "engine": "postgres" }
+```
+
+```hcl
+resource "aws_db_instance" $(*) {
+}
+```
+"#
+        )
+        .unwrap();
+
+        let doc = from_path(file_path).expect("there should have been a doc");
+
+        assert_eq!(
+            vec![Decision::Warn],
+            doc.rules.iter().map(|r| r.decision).collect::<Vec<_>>()
+        );
+        assert_eq!(1, doc.warnings.len());
+        assert_eq!(11, doc.warnings[0].line);
+    }
+
+    #[test]
+    fn positions_count_lines_and_columns_from_one() {
+        assert_eq!((1, 1), position("abc", 0));
+        assert_eq!((2, 3), position("abc\nde", 6));
+    }
+
+    #[test]
+    fn front_matter_sets_how_rules_match() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+
+        write!(
+            file,
+            "{}",
+            r#"---
+matching: case-insensitive
+---
+# Only allow postgres
+
+## Allow
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "postgres"
+}
+```
+"#
+        )
+        .unwrap();
+
+        let doc = from_path(file_path).expect("there should have been a doc");
+
+        assert_eq!(
+            MatchOptions {
+                case_insensitive: true,
+                trim_whitespace: false,
+            },
+            doc.rules[0].options
+        );
+    }
+
+    #[test]
+    fn required_blocks_are_missing_when_no_file_contains_them() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+
+        write!(
+            file,
+            "{}",
+            r#"
+# Pin the terraform version
+
+## Require: a required_version
+
+```
+terraform {
+  required_version = $(*)
+}
+```
+"#
+        )
+        .unwrap();
+
+        let doc = from_path(file_path).expect("there should have been a doc");
+
+        let pinned = terraform::parse_text(
+            r#"
+terraform {
+  required_version = "~> 1.3"
+}
+"#,
+        );
+        let unpinned = terraform::parse_text(r#"locals {}"#);
+
+        assert_eq!(Decision::Require, doc.rules[0].decision);
+        assert!(doc.missing(&[&unpinned, &pinned]).is_empty());
+        assert_eq!(1, doc.missing(&[&unpinned]).len());
+        assert!(doc.matches(&pinned).is_empty());
+    }
 }
@@ -0,0 +1,326 @@
+use super::rule::{Decision, Rule};
+use super::Document;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Something about a rule document that is most likely not what its author meant
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub path: PathBuf,
+    /// The heading of the rule it is about, if it is about a single one
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.rule {
+            Some(rule) => write!(
+                f,
+                "{}: rule \"{}\": {}",
+                self.path.display(),
+                rule,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// Looks at the rules of a document without any terraform to check
+pub fn lint(document: &Document) -> Vec<Finding> {
+    let finding = |rule: Option<&Rule>, message: String| Finding {
+        path: document.path.clone(),
+        rule: rule.map(|r| r.title.clone()),
+        message,
+    };
+    let mut findings = Vec::new();
+
+    let mut seen = HashSet::new();
+    for rule in &document.rules {
+        if !seen.insert(rule.title.as_str()) {
+            findings.push(finding(
+                Some(rule),
+                "the same heading is used by an earlier rule".into(),
+            ));
+        }
+    }
+
+    let reported: Vec<&Rule> = document.rules.iter().filter(|r| is_reported(r)).collect();
+    let requirements = document
+        .rules
+        .iter()
+        .any(|r| r.decision == Decision::Require);
+    if reported.is_empty() && !requirements {
+        findings.push(finding(
+            None,
+            "there is no Deny, Warn, Info or Require rule, nothing is ever reported".into(),
+        ));
+    }
+
+    for (idx, rule) in document.rules.iter().enumerate() {
+        if rule.decision != Decision::Allow {
+            continue;
+        }
+
+        let earlier = document.rules[..idx]
+            .iter()
+            .filter(|r| is_reported(r))
+            .find(|r| r.pattern == rule.pattern);
+        if let Some(earlier) = earlier {
+            findings.push(finding(
+                Some(rule),
+                format!(
+                    "matches exactly what \"{}\" does, one of the two has no effect",
+                    earlier.title
+                ),
+            ));
+        }
+
+        // an Allow only makes a difference to blocks another rule reports as well.
+        // A `Require` is met by a matching block whether or not it is allowed,
+        // so overlapping one alone doesn't count.
+        let meets_a_report = reported.iter().any(|other| {
+            other
+                .targets
+                .iter()
+                .any(|target| rule.targets.iter().any(|own| target.overlaps(own)))
+        });
+        if (!reported.is_empty() || requirements) && !meets_a_report {
+            findings.push(finding(
+                Some(rule),
+                "no Deny, Warn or Info rule matches the same kind of block and an Allow \
+                 doesn't change a Require, it has no effect"
+                    .into(),
+            ));
+        }
+    }
+
+    findings
+}
+
+/// Rules that have a block show up in the report, unless an `Allow` settles it
+fn is_reported(rule: &Rule) -> bool {
+    matches!(
+        rule.decision,
+        Decision::Deny | Decision::Warn | Decision::Info
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_path;
+    use pretty_assertions::assert_eq;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn messages(markdown: &str) -> Vec<String> {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("rule.md");
+        let mut file = File::create(&file_path).unwrap();
+        write!(file, "{}", markdown).unwrap();
+
+        let doc = from_path(file_path).expect("there should have been a doc");
+        lint(&doc)
+            .into_iter()
+            .map(|finding| match finding.rule {
+                Some(rule) => format!("{}: {}", rule, finding.message),
+                None => finding.message,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn well_written_documents_have_no_findings() {
+        let findings = messages(
+            r#"
+# Only allow MySQL rds instances
+
+## Allow: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+
+## Deny: Other engines
+
+```
+resource "aws_db_instance" $(*) {
+}
+```
+"#,
+        );
+
+        assert_eq!(Vec::<String>::new(), findings);
+    }
+
+    #[test]
+    fn finds_rules_that_have_no_effect() {
+        let findings = messages(
+            r#"
+# Databases
+
+## Deny: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+
+## Allow: MySQL after all
+
+```
+resource "aws_db_instance"   $(*) {
+  engine =   "mysql"
+}
+```
+
+## Allow: Buckets
+
+```
+resource "aws_s3_bucket" $(*) {
+}
+```
+"#,
+        );
+
+        assert_eq!(
+            vec![
+                "Allow: MySQL after all: matches exactly what \"Deny: MySQL\" does, one of the two has no effect".to_string(),
+                "Allow: Buckets: no Deny, Warn or Info rule matches the same kind of block and an Allow doesn't change a Require, it has no effect".to_string(),
+            ],
+            findings
+        );
+    }
+
+    #[test]
+    fn finds_repeated_headings() {
+        let findings = messages(
+            r#"
+# Databases
+
+## Allow: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+
+## Allow: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "aurora-mysql"
+}
+```
+
+## Deny: Other engines
+
+```
+resource "aws_db_instance" $(*) {
+}
+```
+"#,
+        );
+
+        assert_eq!(
+            vec!["Allow: MySQL: the same heading is used by an earlier rule".to_string()],
+            findings
+        );
+    }
+
+    #[test]
+    fn finds_documents_that_cant_fail() {
+        let findings = messages(
+            r#"
+# Databases
+
+## Allow: MySQL
+
+```
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+}
+```
+"#,
+        );
+
+        assert_eq!(
+            vec![
+                "there is no Deny, Warn, Info or Require rule, nothing is ever reported"
+                    .to_string()
+            ],
+            findings
+        );
+    }
+
+    #[test]
+    fn warnings_and_notes_are_reported_as_well() {
+        let findings = messages(
+            r#"
+# Databases
+
+## Allow: Encrypted instances
+
+```
+resource "aws_db_instance" $(*) {
+  storage_encrypted = true
+}
+```
+
+## Warn: Unencrypted instances
+
+```
+resource "aws_db_instance" $(*) {
+}
+```
+
+## Info: Old instance types
+
+```
+resource "aws_instance" $(*) {
+  instance_type = "t2.micro"
+}
+```
+"#,
+        );
+
+        assert_eq!(Vec::<String>::new(), findings);
+    }
+
+    #[test]
+    fn allowing_what_a_require_asks_for_has_no_effect() {
+        let findings = messages(
+            r#"
+# Versions
+
+## Require: a terraform version
+
+```
+terraform {
+  required_version = $(*)
+}
+```
+
+## Allow: any terraform version
+
+```
+terraform {
+}
+```
+"#,
+        );
+
+        assert_eq!(
+            vec!["Allow: any terraform version: no Deny, Warn or Info rule matches the same kind of block and an Allow doesn't change a Require, it has no effect".to_string()],
+            findings
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use regex::Regex;
+use std::ops::Range;
+
+lazy_static! {
+    static ref RE: Regex = Regex::new(r#"\$\((?P<operation>[^)]+)\)"#).unwrap();
+    static ref NAMED: Regex =
+        Regex::new(r#"^(?P<name>[a-z_][a-z0-9_]*)(?P<arguments>\s.*)?$"#).unwrap();
+    static ref ARGUMENT: Regex = Regex::new(r#""(?:[^"\\]|\\.)*"|\S+"#).unwrap();
+}
+
+/// What a `$(...)` placeholder in a rule asks for
+#[derive(Debug, PartialEq)]
+pub(crate) enum Operation {
+    /// `$(*)`
+    Any,
+    /// `$("a" || "b")`
+    Or(Vec<String>),
+    /// `$(iam_no_wildcard_admin)` or `$(registry_namespace "our-org")`
+    Named {
+        name: String,
+        arguments: Vec<String>,
+    },
+    Unknown(String),
+}
+
+pub(crate) fn is_placeholder(value: &str) -> bool {
+    matches!(RE.find(value), Some(m) if m.start() == 0 && m.end() == value.len())
+}
+
+/// Where the first placeholder in a longer text is
+pub(crate) fn find(value: &str) -> Option<Range<usize>> {
+    RE.find(value).map(|m| m.start()..m.end())
+}
+
+pub(crate) fn parse(value: &str) -> Option<Operation> {
+    let caps = RE.captures(value)?;
+
+    let operation = caps["operation"].trim();
+    if operation == "*" {
+        return Some(Operation::Any);
+    }
+
+    // This will need extracting into a small parser for operations
+    if operation.contains("||") {
+        let options = operation
+            .split("||")
+            .map(|option| option.trim().to_string())
+            .collect();
+
+        return Some(Operation::Or(options));
+    }
+
+    if let Some(caps) = NAMED.captures(operation) {
+        let arguments = caps
+            .name("arguments")
+            .map(|args| {
+                ARGUMENT
+                    .find_iter(args.as_str())
+                    .map(|arg| arg.as_str().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        return Some(Operation::Named {
+            name: caps["name"].to_string(),
+            arguments,
+        });
+    }
+
+    Some(Operation::Unknown(operation.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_alternatives() {
+        assert_eq!(
+            Some(Operation::Or(vec!["\"a\"".into(), "\"b\"".into()])),
+            parse(r#"$("a" || "b")"#)
+        );
+    }
+
+    #[test]
+    fn parses_named_operations_with_arguments() {
+        assert_eq!(
+            Some(Operation::Named {
+                name: "registry_namespace".into(),
+                arguments: vec!["\"our org\"".into(), "12".into()],
+            }),
+            parse(r#"$(registry_namespace "our org" 12)"#)
+        );
+    }
+
+    #[test]
+    fn only_whole_values_are_placeholders() {
+        assert!(is_placeholder("$(*)"));
+        assert!(!is_placeholder("arn:$(*)"));
+    }
+}
@@ -1,39 +1,159 @@
 use super::ast::AST;
+use super::operation::{self, Operation};
+use super::structured;
+use super::template::{self, Token};
 use core::ops::Range;
 use regex::Regex;
 use std::fmt::{self, write, Write};
 use std::iter::successors;
+use terraform::address::Address;
+use terraform::diagnostic;
+use terraform::iam::{self, Policy};
+use terraform::module_source;
+use terraform::reference;
+use terraform::value::{parse_literal, unquote, Value};
+use terraform::version;
 use terraform::BackingData;
 use tree_sitter::{Node, QueryCursor, QueryPredicate, QueryPredicateArg};
 
-lazy_static! {
-    static ref RE: Regex = Regex::new(r#"\$\((?P<operation>[^)]+)\)"#).unwrap();
-}
-
 // I might want to swap these
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub enum Decision {
     Allow,
     Deny,
+    /// Shown apart from the `Deny`s, and only fails a run when asked to
+    Warn,
+    /// Worth knowing about, like a `Warn` but quieter
+    Info,
+    /// Every root module has to contain a match somewhere in its files
+    Require,
 }
 
 #[derive(Debug, Clone)]
 pub struct NodeInfo {
     pub id: usize,
     pub byte_range: Range<usize>,
+    /// The kind of top-level block that matched, e.g. `resource` or `data`
+    pub kind: String,
+    /// Its labels, e.g. `["aws_iam_policy_document", "admin"]`
+    pub labels: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct MatchResult {
     pub node_info: NodeInfo,
+    /// e.g. `module.warehouse.aws_db_instance.main`, for the blocks that have one
+    pub address: Option<Address>,
     pub decision: Decision,
     pub title: String,
 }
 
+/// How the values in a rule are compared to the ones in terraform
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub struct MatchOptions {
+    pub case_insensitive: bool,
+    pub trim_whitespace: bool,
+}
+
+impl MatchOptions {
+    fn normalise(&self, value: Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.normalise_text(s)),
+            Value::List(items) => {
+                Value::List(items.into_iter().map(|v| self.normalise(v)).collect())
+            }
+            Value::Map(entries) => Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (self.normalise_text(k), self.normalise(v)))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    /// The same as `normalise_text` for a string with substitutions in it
+    fn normalise_tokens(&self, mut tokens: Vec<Token>) -> Vec<Token> {
+        let is_space = |token: &Token| matches!(token, Token::Char(c) if c.is_whitespace());
+        if self.trim_whitespace {
+            let start = tokens
+                .iter()
+                .position(|t| !is_space(t))
+                .unwrap_or(tokens.len());
+            let end = tokens
+                .iter()
+                .rposition(|t| !is_space(t))
+                .map_or(start, |end| end + 1);
+            tokens = tokens[start..end].to_vec();
+        }
+        if self.case_insensitive {
+            tokens = tokens
+                .into_iter()
+                .flat_map(|token| match token {
+                    Token::Char(c) => c.to_lowercase().map(Token::Char).collect(),
+                    Token::Substitution(s) => vec![Token::Substitution(s.to_lowercase())],
+                })
+                .collect();
+        }
+        tokens
+    }
+
+    fn normalise_text(&self, mut text: String) -> String {
+        if self.trim_whitespace {
+            text = text.trim().to_string();
+        }
+        if self.case_insensitive {
+            text = text.to_lowercase();
+        }
+        text
+    }
+}
+
+/// Why a snippet could not be turned into a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleError {
+    pub reason: String,
+    /// The line of the snippet the problem is on, starting at 1
+    pub line: Option<usize>,
+}
+
+impl RuleError {
+    fn new(reason: impl Into<String>) -> Self {
+        RuleError {
+            reason: reason.into(),
+            line: None,
+        }
+    }
+}
+
+/// A block a rule is about, e.g. `resource` blocks of type `aws_db_instance`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Target {
+    pub kind: String,
+    /// `None` for blocks without a type, such as `module` or `variable`
+    pub block_type: Option<String>,
+}
+
+impl Target {
+    /// Whether a block could be matched by rules about both targets
+    pub fn overlaps(&self, other: &Target) -> bool {
+        self.kind == other.kind
+            && match (&self.block_type, &other.block_type) {
+                (Some(this), Some(other)) => this == other,
+                _ => true,
+            }
+    }
+}
+
 #[derive(Debug)]
 pub struct Rule {
     pub title: String,
     pub decision: Decision,
+    pub options: MatchOptions,
+    /// The top-level blocks of the snippet
+    pub targets: Vec<Target>,
+    /// The query the snippet was turned into, the same for snippets that only differ in layout
+    pub(crate) pattern: String,
     result_index: u32,
     query: tree_sitter::Query,
 }
@@ -51,25 +171,51 @@ impl Rule {
         );
 
         write!(output, "(")?;
-        nodes.unwrap().to_sexp(output)?;
+        // writing to a string only fails when there was nothing to write
+        nodes.ok_or(fmt::Error)?.to_sexp(output)?;
         queries.to_sexp(output)?;
         write!(output, ")")
     }
 
-    pub(crate) fn new(title: String, decision: Decision, code: String) -> Result<Self, String> {
+    pub(crate) fn new(title: String, decision: Decision, code: String) -> Result<Self, RuleError> {
+        let snippet = terraform::parse_text(&code);
+        // a query built from `ERROR` nodes matches whatever happens to be broken the same way
+        let diagnostics = diagnostic::diagnostics(&snippet);
+        if let Some(first) = diagnostics.first() {
+            return Err(RuleError {
+                reason: format!(
+                    "the snippet could not be parsed: {}",
+                    diagnostics
+                        .iter()
+                        .map(|d| d.to_string())
+                        .collect::<Vec<String>>()
+                        .join("; ")
+                ),
+                line: Some(first.line),
+            });
+        }
+
+        if let Some(error) = unknown_operations(snippet.root(), &code).into_iter().next() {
+            return Err(error);
+        }
+        let targets = targets(snippet.root(), &code);
+
         let mut rule_as_sexp = String::new();
         Rule::convert_to_sexp(code, &mut rule_as_sexp)
-            .expect("TODO: this should be in infallable? I'm writing to a string...");
-        let query = terraform::query(&rule_as_sexp);
+            .map_err(|_| RuleError::new("There was nothing to match in the code"))?;
+        let query = terraform::query(&rule_as_sexp).map_err(|e| RuleError::new(e.to_string()))?;
 
         match query.capture_names().iter().position(|cap| cap == "result") {
             Some(idx) => Ok(Rule {
                 title,
                 decision,
+                options: MatchOptions::default(),
+                targets,
+                pattern: rule_as_sexp,
                 result_index: idx as u32,
                 query,
             }),
-            None => Err("There was no @result node found".into()),
+            None => Err(RuleError::new("There was no @result node found")),
         }
     }
 
@@ -92,13 +238,14 @@ impl Rule {
                     })
                     .expect("capture of index was not in the list of expected captures of query")
                     };
-                let node_value = |idx: u32| terraform.text(node(idx)).to_string();
 
                 let all_predicates_match = self
                     .query
                     .general_predicates(structural_match.pattern_index)
                     .iter()
-                    .all(|query_pred| query_to_pred(query_pred, node_value).check());
+                    .all(|query_pred| {
+                        query_to_pred(query_pred, terraform, node, &self.options).check()
+                    });
 
                 if !all_predicates_match {
                     return None;
@@ -108,7 +255,10 @@ impl Rule {
                     node_info: NodeInfo {
                         id: result.id(),
                         byte_range: result.byte_range(),
+                        kind: result.kind().into(),
+                        labels: terraform::block_labels(&result, &terraform.input),
                     },
+                    address: Address::of(result, terraform),
                     decision: self.decision,
                     title: self.title.clone(),
                 })
@@ -117,25 +267,185 @@ impl Rule {
     }
 }
 
-fn query_to_pred<F: Fn(u32) -> String>(
+fn query_to_pred<'a, F: Fn(u32) -> Node<'a>>(
     query_pred: &QueryPredicate,
-    node_value: F,
+    terraform: &BackingData,
+    node: F,
+    matching: &MatchOptions,
 ) -> Box<dyn Predicate> {
-    let capture = capture_from(query_pred, node_value);
+    let capture = capture_from(query_pred, node);
     let options = values_from(query_pred);
+    let value = |node: Node| matching.normalise(terraform.value(node));
     match query_pred.operator.as_ref() {
         "or?" => Box::new(Or {
-            capture: capture.unwrap(),
-            options,
+            capture: value(capture.unwrap()),
+            options: options
+                .iter()
+                .map(|option| matching.normalise(parse_literal(option)))
+                .collect(),
+        }),
+        "value?" => Box::new(Equivalent {
+            capture: value(capture.unwrap()),
+            expected: matching
+                .normalise(Value::from_json(&options[0]).expect("value was not valid json")),
+        }),
+        "text?" => Box::new(SameText {
+            capture: matching.normalise_text(unquote(terraform.text(capture.unwrap()))),
+            expected: matching.normalise_text(unquote(&options[0])),
+        }),
+        "template?" => Box::new(Template {
+            pattern: matching.normalise_text(options[0].clone()),
+            target: template::tokens(capture.unwrap(), &terraform.input)
+                .map(|tokens| matching.normalise_tokens(tokens)),
+        }),
+        "structured?" => Box::new(Structured {
+            target: value(capture.unwrap()),
+            pattern: matching
+                .normalise(Value::from_json(&options[0]).expect("pattern was not valid json")),
         }),
-        _ => Box::new(True {}) as Box<dyn Predicate>,
+        operator => named(operator.trim_end_matches('?'), &options, capture, terraform),
+    }
+}
+
+/// Placeholders like `$(iam_no_wildcard_admin)` that come with their own check
+fn named(
+    name: &str,
+    arguments: &[String],
+    capture: Option<Node>,
+    terraform: &BackingData,
+) -> Box<dyn Predicate> {
+    if let Some(check) = iam::Check::from_name(name) {
+        return Box::new(Iam {
+            check,
+            policy: capture.and_then(|node| Policy::from_node(node, terraform)),
+        });
+    }
+
+    if name == "matches" {
+        return Box::new(Matches {
+            regex: arguments
+                .first()
+                .and_then(|pattern| Regex::new(&unquote(pattern)).ok()),
+            text: capture
+                .map(|node| unquote(terraform.text(node)))
+                .unwrap_or_default(),
+        });
+    }
+
+    if name == "no_sensitive_attributes" {
+        return Box::new(NoSensitiveAttributes {
+            references: capture.map(|node| reference::sensitive_references(node, terraform)),
+        });
+    }
+
+    if let Some(check) = version::Check::from_name(name) {
+        return Box::new(Version {
+            check,
+            value: capture.map(|node| terraform.value(node)),
+        });
+    }
+
+    if let Some(check) = module_source::Check::from_name(name, arguments) {
+        return Box::new(ModuleSource {
+            check,
+            value: capture.map(|node| terraform.value(node)),
+        });
+    }
+
+    Box::new(True {})
+}
+
+/// Whether `named` has a check for a placeholder, rather than letting everything through
+fn is_known(name: &str, arguments: &[String]) -> bool {
+    matches!(name, "exact" | "match" | "no_sensitive_attributes")
+        || iam::Check::from_name(name).is_some()
+        || version::Check::from_name(name).is_some()
+        || module_source::Check::from_name(name, arguments).is_some()
+}
+
+/// Placeholders in a snippet that don't stand for anything we can check,
+/// which would otherwise quietly match every value
+fn unknown_operations(node: Node, source: &str) -> Vec<RuleError> {
+    let mut found = Vec::new();
+    if terraform::is_query(node.kind()) {
+        let text = node.utf8_text(source.as_bytes()).unwrap_or_default();
+        let unknown = || {
+            format!(
+                "`{}` is not a known operation or is missing its arguments",
+                text
+            )
+        };
+        let problem = match operation::parse(text) {
+            // a regex that can't be compiled would never match anything
+            Some(Operation::Named { name, arguments }) if name == "match" => {
+                match arguments.first() {
+                    None => Some(format!(
+                        "`{}` needs a regex, e.g. `$(match \"_password$\")`",
+                        text
+                    )),
+                    Some(pattern) => Regex::new(&unquote(pattern))
+                        .err()
+                        .map(|e| format!("`{}` is not a valid regex: {}", text, e)),
+                }
+            }
+            Some(Operation::Named { name, arguments }) if !is_known(&name, &arguments) => {
+                Some(unknown())
+            }
+            Some(Operation::Unknown(_)) => Some(unknown()),
+            _ => None,
+        };
+        if let Some(reason) = problem {
+            found.push(RuleError {
+                reason,
+                line: Some(node.start_position().row + 1),
+            });
+        }
+    }
+
+    // inside of a string only the placeholders the template can compare with text work
+    if node.kind() == "string_literal" {
+        let text = node.utf8_text(source.as_bytes()).unwrap_or_default();
+        let mut offset = 0;
+        while let Some(placeholder) = operation::find(&text[offset..]) {
+            let (start, end) = (offset + placeholder.start, offset + placeholder.end);
+            if !matches!(
+                operation::parse(&text[start..end]),
+                Some(Operation::Any) | Some(Operation::Or(_))
+            ) {
+                found.push(RuleError {
+                    reason: format!(
+                        "`{}` can't be used inside of a string, only `$(*)` and `$(\"a\" || \"b\")` can",
+                        &text[start..end]
+                    ),
+                    line: Some(node.start_position().row + text[..start].matches('\n').count() + 1),
+                });
+            }
+            offset = end;
+        }
+    }
+
+    for child in node.children(&mut node.walk()) {
+        found.extend(unknown_operations(child, source));
     }
+    found
+}
+
+/// The kind and type of every block in a snippet, only resources and data sources have a type
+fn targets(root: Node, source: &str) -> Vec<Target> {
+    terraform::named_children(&root)
+        .into_iter()
+        .map(|block| Target {
+            kind: block.kind().into(),
+            block_type: terraform::named_children(&block)
+                .into_iter()
+                .next()
+                .filter(|label| matches!(label.kind(), "resource_type" | "data_type"))
+                .map(|label| unquote(label.utf8_text(source.as_bytes()).unwrap_or_default())),
+        })
+        .collect()
 }
 
-fn capture_from<F: Fn(u32) -> String>(
-    predicate: &QueryPredicate,
-    extract_value: F,
-) -> Option<String> {
+fn capture_from<T, F: Fn(u32) -> T>(predicate: &QueryPredicate, extract_value: F) -> Option<T> {
     for arg in &predicate.args {
         match arg {
             QueryPredicateArg::Capture(cap) => return Some(extract_value(*cap)),
@@ -164,13 +474,147 @@ trait Predicate: std::fmt::Debug {
 
 #[derive(Debug)]
 struct Or {
-    capture: String,
-    options: Vec<String>,
+    capture: Value,
+    options: Vec<Value>,
 }
 
 impl Predicate for Or {
     fn check(&self) -> bool {
-        self.options.contains(&self.capture)
+        self.options
+            .iter()
+            .any(|option| option.equivalent(&self.capture))
+    }
+}
+
+/// Compares the text of fixed nodes like resource types, labels and attribute names,
+/// unlike `#eq?` this honours the match options
+#[derive(Debug)]
+struct SameText {
+    capture: String,
+    expected: String,
+}
+
+impl Predicate for SameText {
+    fn check(&self) -> bool {
+        self.capture == self.expected
+    }
+}
+
+/// Compares values rather than their source text, so `"5"` matches `5`
+#[derive(Debug)]
+struct Equivalent {
+    capture: Value,
+    expected: Value,
+}
+
+impl Predicate for Equivalent {
+    fn check(&self) -> bool {
+        self.expected.equivalent(&self.capture)
+    }
+}
+
+/// Matches `jsonencode(...)` arguments and heredocs against a pattern
+#[derive(Debug)]
+struct Structured {
+    target: Value,
+    pattern: Value,
+}
+
+impl Predicate for Structured {
+    fn check(&self) -> bool {
+        structured::matches(&self.pattern, &self.target)
+    }
+}
+
+/// Matches strings with placeholders in them, e.g. `"arn:aws:s3:::$(*)/logs/*"`
+#[derive(Debug)]
+struct Template {
+    pattern: String,
+    target: Option<Vec<Token>>,
+}
+
+impl Predicate for Template {
+    fn check(&self) -> bool {
+        match &self.target {
+            Some(tokens) => template::matches(&self.pattern, tokens),
+            None => false,
+        }
+    }
+}
+
+/// Policies we could not read don't pass any check
+#[derive(Debug)]
+struct Iam {
+    check: iam::Check,
+    policy: Option<Policy>,
+}
+
+impl Predicate for Iam {
+    fn check(&self) -> bool {
+        match &self.policy {
+            Some(policy) => self.check.passes(policy),
+            None => false,
+        }
+    }
+}
+
+/// Only plain strings can be checked, a `source = var.source` never passes
+#[derive(Debug)]
+struct ModuleSource {
+    check: module_source::Check,
+    value: Option<Value>,
+}
+
+impl Predicate for ModuleSource {
+    fn check(&self) -> bool {
+        match &self.value {
+            Some(Value::String(value)) => self.check.passes(value),
+            _ => false,
+        }
+    }
+}
+
+/// `$(match "_password$")` runs the regex against the text without quotes,
+/// so it also works on labels such as the name of a variable
+#[derive(Debug)]
+struct Matches {
+    regex: Option<Regex>,
+    text: String,
+}
+
+impl Predicate for Matches {
+    fn check(&self) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(&self.text),
+            None => false,
+        }
+    }
+}
+
+/// `value = $(no_sensitive_attributes)` on outputs that must not leak secrets
+#[derive(Debug)]
+struct NoSensitiveAttributes {
+    references: Option<Vec<String>>,
+}
+
+impl Predicate for NoSensitiveAttributes {
+    fn check(&self) -> bool {
+        matches!(&self.references, Some(references) if references.is_empty())
+    }
+}
+
+#[derive(Debug)]
+struct Version {
+    check: version::Check,
+    value: Option<Value>,
+}
+
+impl Predicate for Version {
+    fn check(&self) -> bool {
+        match &self.value {
+            Some(value) => self.check.passes(value),
+            None => false,
+        }
     }
 }
 
@@ -220,12 +664,20 @@ impl ToSexp for Query {
                     value = join(values),
                 ),
             ),
-            Query::Match { values, reference } => write(
+            Query::Text { value, reference } => write(
                 output,
                 format_args!(
-                    "(#match? @{reference} {value})",
+                    "(#text? @{reference} {value:?})",
                     reference = reference,
-                    value = join(values),
+                    value = value,
+                ),
+            ),
+            Query::Value { value, reference } => write(
+                output,
+                format_args!(
+                    "(#value? @{reference} {value:?})",
+                    reference = reference,
+                    value = value.to_json(),
                 ),
             ),
             Query::Or { values, reference } => write(
@@ -236,6 +688,35 @@ impl ToSexp for Query {
                     value = join(values),
                 ),
             ),
+            Query::Named {
+                name,
+                arguments,
+                reference,
+            } => write(
+                output,
+                format_args!(
+                    "(#{name}? @{reference} {arguments})",
+                    name = name,
+                    reference = reference,
+                    arguments = join(arguments),
+                ),
+            ),
+            Query::Template { pattern, reference } => write(
+                output,
+                format_args!(
+                    "(#template? @{reference} {pattern:?})",
+                    reference = reference,
+                    pattern = pattern,
+                ),
+            ),
+            Query::Structured { pattern, reference } => write(
+                output,
+                format_args!(
+                    "(#structured? @{reference} {value:?})",
+                    reference = reference,
+                    value = pattern.to_json(),
+                ),
+            ),
         }
     }
 }
@@ -246,14 +727,31 @@ enum Query {
         reference: String,
         values: Vec<String>,
     },
-    Match {
+    Text {
         reference: String,
-        values: Vec<String>,
+        value: String,
+    },
+    Value {
+        reference: String,
+        value: Value,
     },
     Or {
         reference: String,
         values: Vec<String>,
     },
+    Named {
+        reference: String,
+        name: String,
+        arguments: Vec<String>,
+    },
+    Structured {
+        reference: String,
+        pattern: Value,
+    },
+    Template {
+        reference: String,
+        pattern: String,
+    },
     Unknown {
         reference: String,
         operation: String,
@@ -290,6 +788,15 @@ enum NodeKind<'a> {
     Query {
         value: String,
     },
+    Structured {
+        value: Value,
+    },
+    Template {
+        pattern: String,
+    },
+    Literal {
+        value: Value,
+    },
     Container {
         kind: String,
         children: Vec<Node<'a>>,
@@ -310,6 +817,18 @@ fn kind<'a>(node: &'a Node, source: &str) -> NodeKind<'a> {
 
     if terraform::is_query(&kind) {
         NodeKind::Query { value }
+    } else if kind == "string_literal" && operation::find(&value).is_some() {
+        NodeKind::Template {
+            pattern: unquote(&value),
+        }
+    } else if is_literal(node) {
+        NodeKind::Literal {
+            value: Value::from_node(*node, source),
+        }
+    } else if is_structured(node) {
+        NodeKind::Structured {
+            value: Value::from_node(*node, source),
+        }
     } else if terraform::is_container(&kind) {
         NodeKind::Container {
             kind,
@@ -320,89 +839,150 @@ fn kind<'a>(node: &'a Node, source: &str) -> NodeKind<'a> {
     }
 }
 
+/// Values we compare by what they mean rather than how they are written.
+/// Keys of maps are included, as `foo = 1` and `"foo" = 1` are the same entry.
+fn is_literal(node: &Node) -> bool {
+    match node.kind() {
+        "string_literal" | "number" | "boolean" | "null" => true,
+        "identifier" => matches!(node.parent(), Some(parent) if parent.kind() == "keyValue"),
+        _ => false,
+    }
+}
+
+/// A placeholder on its own line inside of these is checked against the whole block
+fn is_block(kind: &str) -> bool {
+    kind == "block" || kind == "map"
+}
+
+/// Documents we match by content rather than by their syntax tree
+fn is_structured(node: &Node) -> bool {
+    node.kind() == "heredoc" || terraform::function_name(node) == Some("jsonencode")
+}
+
 fn ast(node: Node, source: &str, generator: &mut UniqueReferences) -> (Option<AST>, Vec<Query>) {
     match kind(&node, source) {
         NodeKind::Unnamed => (None, Vec::new()),
         NodeKind::Query { value } => prcoess_query(value, generator),
+        NodeKind::Template { pattern } => {
+            let reference = generator.next();
+            (
+                Some(AST::Referenced {
+                    reference: reference.clone(),
+                }),
+                vec![Query::Template { reference, pattern }],
+            )
+        }
+        NodeKind::Structured { value } => {
+            let reference = generator.next();
+            (
+                Some(AST::Referenced {
+                    reference: reference.clone(),
+                }),
+                vec![Query::Structured {
+                    reference,
+                    pattern: value,
+                }],
+            )
+        }
         NodeKind::Container { kind, children } => {
             let mut queries = Vec::new();
             let mut children_ast = Vec::new();
+            let mut captures = Vec::new();
             for child in children {
+                let applies_to_block = is_block(&kind) && terraform::is_query(child.kind());
                 match ast(child, &source, generator) {
                     (None, _) => continue,
+                    (Some(AST::Referenced { reference }), mut new_queries) if applies_to_block => {
+                        captures.push(reference);
+                        queries.append(&mut new_queries);
+                    }
+                    (Some(_), _) if applies_to_block => continue,
                     (Some(ast), mut new_queries) => {
                         children_ast.push(ast);
                         queries.append(&mut new_queries);
                     }
                 }
             }
+            let container = AST::Container {
+                kind,
+                children: children_ast,
+            };
+            let node = captures
+                .into_iter()
+                .fold(container, |node, reference| AST::Captured {
+                    node: Box::new(node),
+                    reference,
+                });
+
+            (Some(node), queries)
+        }
+        NodeKind::Literal { value } => {
+            let reference = generator.next();
             (
-                Some(AST::Container {
-                    kind,
-                    children: children_ast,
+                Some(AST::Fixed {
+                    kind: "*".into(),
+                    reference: reference.clone(),
                 }),
-                queries,
+                vec![Query::Value { reference, value }],
             )
         }
         NodeKind::Other { kind, value } => {
             let reference = generator.next();
-            let values = vec![value];
             (
                 Some(AST::Fixed {
                     kind,
                     reference: reference.clone(),
                 }),
-                vec![Query::Eq { reference, values }],
+                vec![Query::Text { reference, value }],
             )
         }
     }
 }
 
 fn prcoess_query(value: String, generator: &mut UniqueReferences) -> (Option<AST>, Vec<Query>) {
-    let caps = RE.captures(&value).unwrap();
-
-    let operation: String = caps["operation"].trim().to_string();
-    if operation == "*" {
-        return (Some(AST::Any), Vec::new());
-    }
+    let operation = match operation::parse(&value) {
+        Some(Operation::Any) | None => return (Some(AST::Any), Vec::new()),
+        Some(operation) => operation,
+    };
 
     let reference = generator.next();
 
-    // This will need extracting into its own module with a small parser for operations
-    if operation.contains("||") {
-        let caps = Regex::new("(?P<left>[^ ]+) \\|\\| (?P<right>.+)")
-            .unwrap()
-            .captures(&operation)
-            .unwrap();
-        let left = caps["left"].trim().to_string();
-        let right = caps["right"].trim().to_string();
-
-        return (
-            Some(AST::Referenced {
-                reference: reference.clone(),
-            }),
-            vec![Query::Or {
-                reference,
-                values: vec![left, right],
-            }],
-        );
-    }
-
-    (
-        Some(AST::Referenced {
+    let query = match operation {
+        // Compares the source text byte for byte
+        Operation::Named { name, arguments } if name == "exact" => Query::Eq {
+            reference: reference.clone(),
+            values: vec![arguments.join(" ")],
+        },
+        // `#match?` is built into tree-sitter and would run the regex on the quoted source text
+        Operation::Named { name, arguments } if name == "match" => Query::Named {
+            reference: reference.clone(),
+            name: "matches".into(),
+            arguments,
+        },
+        Operation::Or(values) => Query::Or {
+            reference: reference.clone(),
+            values,
+        },
+        Operation::Named { name, arguments } => Query::Named {
+            reference: reference.clone(),
+            name,
+            arguments,
+        },
+        Operation::Unknown(operation) => Query::Unknown {
             reference: reference.clone(),
-        }),
-        vec![Query::Unknown {
-            reference,
             operation,
-        }],
-    )
+        },
+        Operation::Any => unreachable!("any matches without a reference"),
+    };
+
+    (Some(AST::Referenced { reference }), vec![query])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
+    use terraform::scope::Scope;
 
     #[test]
     fn turns_a_rule_into_s_expression() {
@@ -417,7 +997,43 @@ mod tests {
         Rule::convert_to_sexp(code, &mut buffer).unwrap();
 
         assert_eq!(
-            r#"((configuration (resource (resource_type) @1 (*) (block (attribute (identifier) @2 (*) ) ) ) @result )(#eq? @1 "\"aws_rds_instance\"") (#eq? @2 "size") )"#,
+            r#"((configuration (resource (resource_type) @1 (*) (block (attribute (identifier) @2 (*) ) ) ) @result )(#text? @1 "\"aws_rds_instance\"") (#text? @2 "size") )"#,
+            buffer
+        )
+    }
+
+    #[test]
+    fn match_placeholders_dont_use_the_built_in_predicate() {
+        let code = r#"
+              variable $(match "_password$") {
+              }
+            "#
+        .into();
+
+        let mut buffer = String::new();
+        Rule::convert_to_sexp(code, &mut buffer).unwrap();
+
+        assert!(
+            buffer.contains(r#"(#matches? @1 "\"_password$\"")"#),
+            "{}",
+            buffer
+        );
+    }
+
+    #[test]
+    fn compares_literal_values_through_a_predicate() {
+        let code = r#"
+              resource "aws_instance" $(*) {
+                 count = 5
+              }
+            "#
+        .into();
+
+        let mut buffer = String::new();
+        Rule::convert_to_sexp(code, &mut buffer).unwrap();
+
+        assert_eq!(
+            r#"((configuration (resource (resource_type) @1 (*) (block (attribute (identifier) @2 (*) @3 ) ) ) @result )(#text? @1 "\"aws_instance\"") (#text? @2 "count") (#value? @3 "5.0") )"#,
             buffer
         )
     }
@@ -454,5 +1070,644 @@ mod tests {
         let m = r.matches(&backing_data);
 
         assert_eq!(2, m.len());
+
+        let addresses: Vec<String> = m
+            .iter()
+            .filter_map(|m| m.address.as_ref())
+            .map(|address| address.to_string())
+            .collect();
+        assert_eq!(vec!["aws_rds_instance.a", "aws_rds_instance.b"], addresses);
+    }
+
+    #[test]
+    fn matches_into_json_policies() {
+        let r = Rule::new(
+            "Admin policies".into(),
+            Decision::Deny,
+            r#"
+            resource "aws_iam_policy" $(*) {
+              policy = jsonencode({
+                Statement = [{ Effect = "Allow", Action = "*" }]
+              })
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+resource "aws_iam_policy" "heredoc" {
+  policy = <<EOF
+{
+  "Version": "2012-10-17",
+  "Statement": [{"Effect": "Allow", "Action": ["*"], "Resource": "*"}]
+}
+EOF
+}
+
+resource "aws_iam_policy" "encoded" {
+  policy = jsonencode({
+    Statement = [{ Effect = "Allow", Action = "*", Resource = "*" }]
+  })
+}
+
+resource "aws_iam_policy" "read_only" {
+  policy = jsonencode({
+    Statement = [{ Effect = "Allow", Action = "s3:GetObject", Resource = "*" }]
+  })
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(2, m.len());
+    }
+
+    #[test]
+    fn named_placeholders_check_iam_policies() {
+        let r = Rule::new(
+            "Policies without admin rights".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_iam_policy" $(*) {
+              policy = $(iam_no_wildcard_admin)
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+data "aws_iam_policy_document" "admin" {
+  statement {
+    actions   = ["*"]
+    resources = ["*"]
+  }
+}
+
+resource "aws_iam_policy" "from_document" {
+  policy = data.aws_iam_policy_document.admin.json
+}
+
+resource "aws_iam_policy" "encoded" {
+  policy = jsonencode({
+    Statement = [{ Effect = "Allow", Action = "s3:GetObject", Resource = "*" }]
+  })
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+    }
+
+    #[test]
+    fn matches_other_top_level_blocks() {
+        let r = Rule::new(
+            "Policy documents without admin rights".into(),
+            Decision::Allow,
+            r#"
+            data "aws_iam_policy_document" $(*) {
+              $(iam_no_wildcard_admin)
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+data "aws_iam_policy_document" "admin" {
+  statement {
+    actions   = ["*"]
+    resources = ["*"]
+  }
+}
+
+data "aws_iam_policy_document" "read" {
+  statement {
+    actions   = ["s3:GetObject"]
+    resources = ["*"]
+  }
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+        assert_eq!("data", m[0].node_info.kind);
+        assert_eq!(
+            vec!["aws_iam_policy_document", "read"],
+            m[0].node_info.labels
+        );
+    }
+
+    #[test]
+    fn named_placeholders_check_module_sources() {
+        let r = Rule::new(
+            "Modules from our own registry".into(),
+            Decision::Allow,
+            r#"
+            module $(*) {
+              source  = $(registry_namespace "our-org")
+              version = $(pinned_version)
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+module "pinned" {
+  source  = "our-org/vpc/aws"
+  version = "3.2.1"
+}
+
+module "ranged" {
+  source  = "our-org/vpc/aws"
+  version = "~> 3.2"
+}
+
+module "public" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "3.2.1"
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+        assert_eq!(vec!["pinned"], m[0].node_info.labels);
+    }
+
+    #[test]
+    fn checks_the_parts_of_variables() {
+        let r = Rule::new(
+            "Documented variables".into(),
+            Decision::Allow,
+            r#"
+            variable $(*) {
+              type        = $(*)
+              description = $(*)
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+variable "documented" {
+  type        = list(string)
+  description = "Some names"
+}
+
+variable "untyped" {
+  description = "Anything goes"
+}
+
+variable "bare" {}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+        assert_eq!(vec!["documented"], m[0].node_info.labels);
+    }
+
+    #[test]
+    fn matches_names_and_types_of_variables() {
+        let r = Rule::new(
+            "Sensitive secrets".into(),
+            Decision::Allow,
+            r#"
+            variable $(match "_password$|_token$") {
+              type      = object({ value = string })
+              sensitive = true
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+variable "db_password" {
+  type      = object({ value = string, rotated = bool })
+  sensitive = true
+}
+
+variable "api_token" {
+  type      = object({ value = string })
+  sensitive = false
+}
+
+variable "region" {
+  type      = object({ value = string })
+  sensitive = true
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+        assert_eq!(vec!["db_password"], m[0].node_info.labels);
+    }
+
+    #[test]
+    fn follows_output_values_to_sensitive_attributes() {
+        let r = Rule::new(
+            "Outputs without secrets".into(),
+            Decision::Allow,
+            r#"
+            output $(*) {
+              value = $(no_sensitive_attributes)
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+output "address" {
+  value = aws_db_instance.main.address
+}
+
+output "password" {
+  value = aws_db_instance.main.password
+}
+
+output "connection" {
+  value = "postgres://admin:${random_password.db.result}@${aws_db_instance.main.address}"
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+        assert_eq!(vec!["address"], m[0].node_info.labels);
+    }
+
+    #[test]
+    fn compares_against_resolved_variables_and_locals() {
+        let r = Rule::new(
+            "Postgres only".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_db_instance" $(*) {
+              engine = "postgres"
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+variable "engine" {
+  default = "postgres"
+}
+
+locals {
+  engine = var.engine
+}
+
+resource "aws_db_instance" "from_local" {
+  engine = local.engine
+}
+
+resource "aws_db_instance" "unknown" {
+  engine = var.other_engine
+}
+        "#;
+
+        let mut backing_data = terraform::parse_text(&terraform_text);
+        backing_data.scope = Scope::root(&[&backing_data], &[]);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+        assert_eq!(vec!["aws_db_instance", "from_local"], m[0].node_info.labels);
+    }
+
+    #[test]
+    fn compares_against_computed_expressions() {
+        let r = Rule::new(
+            "Six replicas".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_instance" $(*) {
+              count = 6
+              name  = "web-prod"
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+variable "environment" {
+  default = "prod"
+}
+
+resource "aws_instance" "computed" {
+  count = 2 * 3
+  name  = format("web-%s", var.environment)
+}
+
+resource "aws_instance" "unknown" {
+  count = 2 * var.replicas
+  name  = format("web-%s", var.environment)
+}
+        "#;
+
+        let mut backing_data = terraform::parse_text(&terraform_text);
+        backing_data.scope = Scope::root(&[&backing_data], &[]);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+        assert_eq!(vec!["aws_instance", "computed"], m[0].node_info.labels);
+    }
+
+    #[test]
+    fn matches_placeholders_inside_of_strings() {
+        let r = Rule::new(
+            "Log buckets".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_s3_bucket_policy" $(*) {
+              resource = "arn:aws:s3:::$(*)/logs/*"
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+resource "aws_s3_bucket_policy" "interpolated" {
+  resource = "arn:aws:s3:::${var.bucket}/logs/*"
+}
+
+resource "aws_s3_bucket_policy" "literal" {
+  resource = "arn:aws:s3:::my-bucket/logs/*"
+}
+
+resource "aws_s3_bucket_policy" "other" {
+  resource = "arn:aws:s3:::${var.bucket}/audit/*"
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(2, m.len());
+    }
+
+    #[test]
+    fn named_placeholders_inside_of_strings_are_refused() {
+        let error = Rule::new(
+            "Exact names".into(),
+            Decision::Deny,
+            r#"
+resource "aws_s3_bucket" $(*) {
+  bucket = "logs-$(pinned_version)"
+}
+"#
+            .into(),
+        )
+        .unwrap_err();
+
+        assert!(error.reason.contains("can't be used inside of a string"));
+        assert_eq!(Some(3), error.line);
+    }
+
+    #[test]
+    fn match_placeholders_without_a_valid_regex_are_refused() {
+        let rule = |placeholder: &str| {
+            Rule::new(
+                "Secrets".into(),
+                Decision::Deny,
+                format!("\nvariable {} {{\n  sensitive = false\n}}\n", placeholder),
+            )
+        };
+
+        let missing = rule("$(match)").unwrap_err();
+        assert!(
+            missing.reason.contains("needs a regex"),
+            "{}",
+            missing.reason
+        );
+        assert_eq!(Some(2), missing.line);
+
+        let invalid = rule(r#"$(match "[")"#).unwrap_err();
+        assert!(
+            invalid.reason.contains("is not a valid regex"),
+            "{}",
+            invalid.reason
+        );
+        assert_eq!(Some(2), invalid.line);
+    }
+
+    #[test]
+    fn compares_values_rather_than_source_text() {
+        let r = Rule::new(
+            "Five instances".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_instance" $(*) {
+              count = 5
+              tags = {
+                "Name" = "web"
+              }
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+resource "aws_instance" "number" {
+  count = 5.0
+  tags = {
+    Name = "web"
+  }
+}
+
+resource "aws_instance" "string" {
+  count = "5"
+  tags = {
+    "Name" = "web"
+  }
+}
+
+resource "aws_instance" "other" {
+  count = 6
+  tags = {
+    Name = "web"
+  }
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(2, m.len());
+    }
+
+    #[test]
+    fn exact_compares_the_source_text() {
+        let r = Rule::new(
+            "Quoted count".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_instance" $(*) {
+              count = $(exact "5")
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+resource "aws_instance" "number" {
+  count = 5
+}
+
+resource "aws_instance" "string" {
+  count = "5"
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        let m = r.matches(&backing_data);
+
+        assert_eq!(1, m.len());
+    }
+
+    #[test]
+    fn match_options_ignore_case_and_surrounding_whitespace() {
+        let mut r = Rule::new(
+            "Postgres".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_db_instance" $(*) {
+              engine = $("postgres" || "aurora-postgresql")
+              instance_class = "db.t3.micro"
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let terraform_text = r#"
+resource "aws_db_instance" "shouting" {
+  engine         = "POSTGRES"
+  instance_class = " DB.T3.MICRO "
+}
+        "#;
+
+        let backing_data = terraform::parse_text(&terraform_text);
+
+        assert_eq!(0, r.matches(&backing_data).len());
+
+        r.options = MatchOptions {
+            case_insensitive: true,
+            trim_whitespace: true,
+        };
+
+        assert_eq!(1, r.matches(&backing_data).len());
+    }
+
+    #[test]
+    fn match_options_apply_to_strings_with_placeholders() {
+        let mut r = Rule::new(
+            "Log buckets".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_s3_bucket" $(*) {
+              bucket = "logs-$(*)"
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let backing_data = terraform::parse_text(
+            r#"
+resource "aws_s3_bucket" "shouting" {
+  bucket = " LOGS-${var.environment} "
+}
+        "#,
+        );
+
+        assert_eq!(0, r.matches(&backing_data).len());
+
+        r.options = MatchOptions {
+            case_insensitive: true,
+            trim_whitespace: true,
+        };
+
+        assert_eq!(1, r.matches(&backing_data).len());
+    }
+
+    #[test]
+    fn match_options_apply_to_labels_as_well() {
+        let mut r = Rule::new(
+            "Main database".into(),
+            Decision::Allow,
+            r#"
+            resource "aws_db_instance" "main" {
+            }
+            "#
+            .into(),
+        )
+        .unwrap();
+
+        let backing_data = terraform::parse_text(
+            r#"
+resource "aws_db_instance" "MAIN" {
+}
+        "#,
+        );
+
+        assert_eq!(0, r.matches(&backing_data).len());
+
+        r.options = MatchOptions {
+            case_insensitive: true,
+            trim_whitespace: false,
+        };
+
+        assert_eq!(1, r.matches(&backing_data).len());
+    }
+
+    #[test]
+    fn snippets_that_dont_parse_are_refused() {
+        let error = Rule::new(
+            "Unclosed".into(),
+            Decision::Deny,
+            r#"
+resource "aws_db_instance" $(*) {
+  engine = "mysql"
+"#
+            .into(),
+        )
+        .unwrap_err();
+
+        assert!(error.reason.starts_with("the snippet could not be parsed"));
+        assert!(error.line.is_some());
     }
 }
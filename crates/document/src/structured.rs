@@ -0,0 +1,89 @@
+use super::operation::{self, Operation};
+use terraform::value::{parse_literal, Value};
+
+/// Checks whether the `target` contains everything the `pattern` asks for.
+///
+/// Maps only need the keys mentioned in the pattern and every element of
+/// a list in the pattern has to be found somewhere in the target list.
+/// A single value in the pattern also matches a list containing it, as
+/// policies can use `"s3:GetObject"` and `["s3:GetObject"]` interchangeably.
+pub(crate) fn matches(pattern: &Value, target: &Value) -> bool {
+    match (pattern, target) {
+        (Value::String(p), _) if operation::is_placeholder(p) => placeholder(p, target),
+        (Value::Map(p), Value::Map(t)) => p
+            .iter()
+            .all(|(key, value)| matches!(t.get(key), Some(other) if matches(value, other))),
+        (Value::List(p), Value::List(t)) => p
+            .iter()
+            .all(|value| t.iter().any(|other| matches(value, other))),
+        (_, Value::List(t)) => t.iter().any(|other| matches(pattern, other)),
+        (_, Value::String(t)) => match Value::from_json(t) {
+            Some(document @ Value::Map(_)) | Some(document @ Value::List(_)) => {
+                matches(pattern, &document)
+            }
+            _ => pattern == target,
+        },
+        _ => pattern == target,
+    }
+}
+
+fn placeholder(pattern: &str, target: &Value) -> bool {
+    match operation::parse(pattern) {
+        Some(Operation::Any) => true,
+        Some(Operation::Or(options)) => options
+            .iter()
+            .any(|option| &parse_literal(option) == target),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(text: &str) -> Value {
+        Value::from_json(text).expect("invalid json in test")
+    }
+
+    #[test]
+    fn only_keys_in_the_pattern_need_to_be_present() {
+        let pattern = json(r#"{"Statement": [{"Effect": "Allow", "Action": "*"}]}"#);
+        let target = json(
+            r#"{"Version": "2012-10-17", "Statement": [
+                {"Effect": "Deny", "Action": "s3:*"},
+                {"Effect": "Allow", "Action": "*", "Resource": "*"}
+            ]}"#,
+        );
+
+        assert!(matches(&pattern, &target));
+    }
+
+    #[test]
+    fn single_values_match_lists_containing_them() {
+        let pattern = json(r#"{"Action": "*"}"#);
+        let target = json(r#"{"Action": ["s3:GetObject", "*"]}"#);
+
+        assert!(matches(&pattern, &target));
+    }
+
+    #[test]
+    fn placeholders_inside_the_document_are_applied() {
+        let pattern = json(r#"{"Effect": "$(\"Allow\" || \"Deny\")", "Action": "$(*)"}"#);
+
+        assert!(matches(
+            &pattern,
+            &json(r#"{"Effect": "Allow", "Action": "s3:*"}"#)
+        ));
+        assert!(!matches(
+            &pattern,
+            &json(r#"{"Effect": "Maybe", "Action": "s3:*"}"#)
+        ));
+    }
+
+    #[test]
+    fn missing_keys_do_not_match() {
+        let pattern = json(r#"{"Condition": "$(*)"}"#);
+
+        assert!(!matches(&pattern, &json(r#"{"Effect": "Allow"}"#)));
+    }
+}
@@ -0,0 +1,171 @@
+use super::operation::{self, Operation};
+use std::collections::HashMap;
+use terraform::value::{parse_literal, unquote, Value};
+use tree_sitter::Node;
+
+/// A piece of a terraform string: either plain text or a `${...}` substitution
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    Char(char),
+    Substitution(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// Splits the text of a string in a rule, e.g. `arn:aws:s3:::$(*)/logs/*`,
+/// into literal text and placeholders
+fn parts(pattern: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut rest = pattern;
+
+    while let Some(found) = operation::find(rest) {
+        if found.start > 0 {
+            parts.push(Part::Literal(rest[..found.start].to_string()));
+        }
+        parts.push(Part::Placeholder(rest[found.clone()].to_string()));
+        rest = &rest[found.end..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Literal(rest.to_string()));
+    }
+
+    parts
+}
+
+/// The text and substitutions of a `string_literal` or `interpolation_string`
+pub(crate) fn tokens(node: Node, source: &str) -> Option<Vec<Token>> {
+    let text = |n: Node| {
+        n.utf8_text(source.as_bytes())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    match node.kind() {
+        "string_literal" => Some(unquote(&text(node)).chars().map(Token::Char).collect()),
+        "interpolation_string" => Some(
+            terraform::named_children(&node)
+                .into_iter()
+                .flat_map(|segment| match segment.kind() {
+                    "interpolation_substitution" => vec![Token::Substitution(text(segment))],
+                    _ => unquote(&text(segment)).chars().map(Token::Char).collect(),
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn render(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Char(c) => c.to_string(),
+            Token::Substitution(s) => s.clone(),
+        })
+        .collect()
+}
+
+fn accepts(placeholder: &str, tokens: &[Token]) -> bool {
+    match operation::parse(placeholder) {
+        Some(Operation::Any) => true,
+        Some(Operation::Or(options)) => {
+            let text = Value::String(render(tokens));
+            options.iter().any(|option| parse_literal(option) == text)
+        }
+        _ => false,
+    }
+}
+
+/// Placeholders can cover any number of tokens, so the same rest of a pattern gets tried
+/// against the same rest of a string many times. `known` remembers how that went, keyed by
+/// how many parts and tokens are left, which keeps several `$(*)` in a string from
+/// taking exponential time.
+fn matches_from(
+    parts: &[Part],
+    tokens: &[Token],
+    known: &mut HashMap<(usize, usize), bool>,
+) -> bool {
+    if let Some(matched) = known.get(&(parts.len(), tokens.len())) {
+        return *matched;
+    }
+
+    let matched = match parts.split_first() {
+        None => tokens.is_empty(),
+        Some((Part::Literal(literal), rest)) => {
+            let expected: Vec<Token> = literal.chars().map(Token::Char).collect();
+            tokens.starts_with(&expected) && matches_from(rest, &tokens[expected.len()..], known)
+        }
+        Some((Part::Placeholder(placeholder), rest)) => (0..=tokens.len()).any(|end| {
+            accepts(placeholder, &tokens[..end]) && matches_from(rest, &tokens[end..], known)
+        }),
+    };
+
+    known.insert((parts.len(), tokens.len()), matched);
+    matched
+}
+
+/// Checks a string from a rule containing placeholders against a string in terraform.
+/// Literal text has to match exactly, while placeholders can stand for any
+/// mix of text and `${...}` substitutions.
+pub(crate) fn matches(pattern: &str, tokens: &[Token]) -> bool {
+    matches_from(&parts(pattern), tokens, &mut HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn tokens_of(text: &str) -> Vec<Token> {
+        text.chars().map(Token::Char).collect()
+    }
+
+    #[test]
+    fn splits_patterns_into_parts() {
+        assert_eq!(
+            vec![
+                Part::Literal("arn:aws:s3:::".into()),
+                Part::Placeholder("$(*)".into()),
+                Part::Literal("/logs/*".into()),
+            ],
+            parts("arn:aws:s3:::$(*)/logs/*")
+        );
+    }
+
+    #[test]
+    fn placeholders_cover_substitutions() {
+        let mut tokens = tokens_of("arn:aws:s3:::");
+        tokens.push(Token::Substitution("${var.bucket}".into()));
+        tokens.extend(tokens_of("/logs/*"));
+
+        assert!(matches("arn:aws:s3:::$(*)/logs/*", &tokens));
+        assert!(!matches("arn:aws:s3:::$(*)/audit/*", &tokens));
+    }
+
+    #[test]
+    fn literal_text_does_not_match_inside_substitutions() {
+        let tokens = vec![Token::Substitution("${var.logs}".into())];
+
+        assert!(!matches("$(*)logs$(*)", &tokens));
+    }
+
+    #[test]
+    fn alternatives_are_compared_with_the_covered_text() {
+        let tokens = tokens_of("prod-db");
+
+        assert!(matches(r#"$("prod" || "staging")-db"#, &tokens));
+        assert!(!matches(r#"$("dev" || "staging")-db"#, &tokens));
+    }
+
+    #[test]
+    fn many_placeholders_in_one_string_stay_fast() {
+        let tokens = tokens_of(&"a".repeat(60));
+        let pattern = format!("{}b", "$(*)".repeat(20));
+
+        assert!(!matches(&pattern, &tokens));
+    }
+}
@@ -1,17 +1,45 @@
 use serde::Serialize;
 use std::collections::HashMap;
-use std::io::Write;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use terraform::diagnostic::SyntaxErrors;
 use terraform::BackingData;
 use tinytemplate::{format_unescaped, TinyTemplate};
 
-use document::rule::{Decision, MatchResult};
+use document::conflict::Conflict;
+use document::rule::{Decision, MatchResult, NodeInfo, Rule};
 
 static TEMPLATE: &str = r#"{{ for value in success }}
 {value} ... ✅
 {{ endfor }}
 {{ for failure in failures }}
-{failure.file} ... ❌
+{failure.file}: {failure.block} ... ❌
 {failure.code}
+{{ endfor }}
+{{ for warning in warnings }}
+{warning.file}: {warning.block} ... ⚠️
+{warning.code}
+{{ endfor }}
+{{ for note in notes }}
+{note.file}: {note.block} ... ℹ️
+{note.code}
+{{ endfor }}"#;
+
+static MISSING: &str = r#"{{ for title in titles }}
+{directory} ... ❌
+missing: {title}
+{{ endfor }}"#;
+
+static SYNTAX_ERRORS: &str = r#"{{ for error in errors }}
+{error.file}:{error.line}:{error.column}: {error.message} ... ❌
+{{ endfor }}"#;
+
+static CONFLICTS: &str = r#"{{ for conflict in conflicts }}
+{conflict.file}: {conflict.block} ... ❌
+allowed by: {conflict.allowed_by}
+denied by: {conflict.denied_by}
 {{ endfor }}"#;
 
 type NodeId = usize;
@@ -19,18 +47,75 @@ type NodeId = usize;
 #[derive(Debug, Serialize)]
 struct Failure {
     file: String,
+    block: String,
     code: String,
 }
 
+#[derive(Debug, Serialize)]
+struct Missing {
+    directory: String,
+    titles: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SyntaxError {
+    file: String,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Disagreement {
+    file: String,
+    block: String,
+    allowed_by: String,
+    denied_by: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Conflicts {
+    conflicts: Vec<Disagreement>,
+}
+
+#[derive(Debug, Serialize)]
+struct Unparsable {
+    errors: Vec<SyntaxError>,
+}
+
 #[derive(Debug, Default, Serialize)]
 struct Context {
     failures: Vec<Failure>,
+    warnings: Vec<Failure>,
+    notes: Vec<Failure>,
     success: Vec<String>,
 }
 
+/// How bad the worst thing a report has shown is, for deciding whether a run failed
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    Info,
+    Warn,
+    Deny,
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(Severity::Info),
+            "warn" => Ok(Severity::Warn),
+            "deny" => Ok(Severity::Deny),
+            other => Err(format!("`{}` is not one of deny, warn or info", other)),
+        }
+    }
+}
+
 pub struct StdoutReport<'a, W: Write> {
     output: W,
     template: TinyTemplate<'a>,
+    severity: Option<Severity>,
 }
 
 impl<'a, W: Write> StdoutReport<'a, W> {
@@ -40,18 +125,90 @@ impl<'a, W: Write> StdoutReport<'a, W> {
         template
             .add_template("success_and_failure", TEMPLATE)
             .unwrap();
-        StdoutReport { output, template }
+        template.add_template("missing", MISSING).unwrap();
+        template
+            .add_template("syntax_errors", SYNTAX_ERRORS)
+            .unwrap();
+        template.add_template("conflicts", CONFLICTS).unwrap();
+        StdoutReport {
+            output,
+            template,
+            severity: None,
+        }
+    }
+
+    fn saw(&mut self, severity: Severity) {
+        self.severity = self.severity.max(Some(severity));
+    }
+}
+
+/// What can go wrong presenting results
+#[derive(Debug)]
+pub enum Error {
+    /// The results did not fit the template
+    Template(tinytemplate::error::Error),
+    /// The results could not be written out
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Template(e) => write!(f, "could not render the report: {}", e),
+            Error::Io(e) => write!(f, "could not write the report: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Template(e) => Some(e),
+            Error::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<tinytemplate::error::Error> for Error {
+    fn from(e: tinytemplate::error::Error) -> Self {
+        Error::Template(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
     }
 }
 
 /// Present the results to a user in a meaningful way
 pub trait Report {
-    fn about(&mut self, terraform: &BackingData, match_results: Vec<MatchResult>);
+    fn about(
+        &mut self,
+        terraform: &BackingData,
+        match_results: Vec<MatchResult>,
+    ) -> Result<(), Error>;
+
+    /// `Require` rules that a root module did not satisfy
+    fn missing(&mut self, directory: &Path, rules: Vec<&Rule>) -> Result<(), Error>;
+
+    /// Files that were not checked as they could not be parsed
+    fn syntax_errors(&mut self, errors: &SyntaxErrors) -> Result<(), Error>;
+
+    /// Blocks the documents disagree on that were not settled by a precedence
+    fn conflicts(&mut self, terraform: &BackingData, conflicts: &[Conflict]) -> Result<(), Error>;
+
+    /// The worst of what has been shown so far, `None` when everything passed
+    fn severity(&self) -> Option<Severity>;
 }
 
 impl<'a, W: Write> Report for StdoutReport<'a, W> {
     // This needs to a single call, not a giant loop...
-    fn about(&mut self, terraform: &BackingData, match_results: Vec<MatchResult>) {
+    fn about(
+        &mut self,
+        terraform: &BackingData,
+        match_results: Vec<MatchResult>,
+    ) -> Result<(), Error> {
         let mut context = Context::default();
         if match_results.is_empty() {
             context.success.push(terraform.path.clone());
@@ -67,27 +224,192 @@ impl<'a, W: Write> Report for StdoutReport<'a, W> {
         }
 
         for results in results_for_node.values() {
+            // an Allow settles a block, otherwise the most severe of the rules is shown
             let any_allow = results.iter().any(|m| m.decision == Decision::Allow);
-            if !any_allow {
-                let dennial = results
-                    .iter()
-                    .find(|m| m.decision == Decision::Deny)
-                    .unwrap();
+            let first = |decision| results.iter().find(|m| m.decision == decision);
+            let shown = [
+                (Decision::Deny, Severity::Deny),
+                (Decision::Warn, Severity::Warn),
+                (Decision::Info, Severity::Info),
+            ]
+            .iter()
+            .find_map(|(decision, severity)| first(*decision).map(|m| (m, *severity)));
 
-                context.failures.push(Failure {
-                    file: terraform.path.clone(),
-                    code: terraform
-                        .text_range(&dennial.node_info.byte_range)
-                        .to_string(),
-                });
-            } else {
-                context.success.push(terraform.path.clone())
+            match shown {
+                Some((result, severity)) if !any_allow => {
+                    self.saw(severity);
+                    let listed = match severity {
+                        Severity::Deny => &mut context.failures,
+                        Severity::Warn => &mut context.warnings,
+                        Severity::Info => &mut context.notes,
+                    };
+                    listed.push(failure(terraform, result));
+                }
+                _ => context.success.push(terraform.path.clone()),
             }
         }
-        let rendered = self
-            .template
-            .render("success_and_failure", &context)
-            .unwrap();
-        write!(self.output, "{}", rendered).expect("TODO: should we lift this?");
+        let rendered = self.template.render("success_and_failure", &context)?;
+        write!(self.output, "{}", rendered)?;
+        Ok(())
+    }
+
+    fn missing(&mut self, directory: &Path, rules: Vec<&Rule>) -> Result<(), Error> {
+        // files at the top level of the repository have no parent directory
+        let directory = if directory.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            directory
+        };
+        self.saw(Severity::Deny);
+        let context = Missing {
+            directory: directory.to_string_lossy().into(),
+            titles: rules.iter().map(|r| r.title.clone()).collect(),
+        };
+        let rendered = self.template.render("missing", &context)?;
+        write!(self.output, "{}", rendered)?;
+        Ok(())
+    }
+
+    fn syntax_errors(&mut self, errors: &SyntaxErrors) -> Result<(), Error> {
+        let context = Unparsable {
+            errors: errors
+                .diagnostics
+                .iter()
+                .map(|diagnostic| SyntaxError {
+                    file: errors.path.clone(),
+                    line: diagnostic.line,
+                    column: diagnostic.column,
+                    message: diagnostic.message.clone(),
+                })
+                .collect(),
+        };
+        let rendered = self.template.render("syntax_errors", &context)?;
+        write!(self.output, "{}", rendered)?;
+        Ok(())
+    }
+
+    fn conflicts(&mut self, terraform: &BackingData, conflicts: &[Conflict]) -> Result<(), Error> {
+        self.saw(Severity::Deny);
+        let context = Conflicts {
+            conflicts: conflicts
+                .iter()
+                .map(|conflict| Disagreement {
+                    file: terraform.path.clone(),
+                    block: match &conflict.address {
+                        Some(address) => address.to_string(),
+                        None => block_name(&conflict.node_info),
+                    },
+                    allowed_by: conflict.allowed_by.join(", "),
+                    denied_by: conflict.denied_by.join(", "),
+                })
+                .collect(),
+        };
+        let rendered = self.template.render("conflicts", &context)?;
+        write!(self.output, "{}", rendered)?;
+        Ok(())
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        self.severity
+    }
+}
+
+fn failure(terraform: &BackingData, result: &MatchResult) -> Failure {
+    Failure {
+        file: terraform.path.clone(),
+        block: match &result.address {
+            Some(address) => address.to_string(),
+            None => block_name(&result.node_info),
+        },
+        code: terraform
+            .text_range(&result.node_info.byte_range)
+            .to_string(),
+    }
+}
+
+/// The header of a block, e.g. `data "aws_iam_policy_document" "admin"`
+fn block_name(node_info: &NodeInfo) -> String {
+    node_info
+        .labels
+        .iter()
+        .fold(node_info.kind.clone(), |name, label| {
+            format!("{} \"{}\"", name, label)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    static TERRAFORM: &str = r#"resource "aws_db_instance" "main" {
+  engine = "mysql"
+}
+"#;
+
+    /// What `about` prints for a single block that matched rules with the given decisions
+    fn rendered(decisions: &[Decision]) -> (String, Option<Severity>) {
+        let terraform = terraform::parse_text(TERRAFORM);
+        let results = decisions
+            .iter()
+            .map(|decision| MatchResult {
+                node_info: NodeInfo {
+                    id: 1,
+                    byte_range: 0..TERRAFORM.len() - 1,
+                    kind: "resource".into(),
+                    labels: vec!["aws_db_instance".into(), "main".into()],
+                },
+                address: None,
+                decision: *decision,
+                title: format!("{:?}: MySQL", decision),
+            })
+            .collect();
+
+        let mut output = Vec::new();
+        let mut report = StdoutReport::new(&mut output);
+        report.about(&terraform, results).unwrap();
+        let severity = report.severity();
+        drop(report);
+
+        (String::from_utf8(output).unwrap(), severity)
+    }
+
+    #[test]
+    fn the_most_severe_decision_decides_the_section() {
+        let (output, severity) = rendered(&[Decision::Info, Decision::Warn, Decision::Deny]);
+        assert!(output.contains("unknown: resource \"aws_db_instance\" \"main\" ... ❌"));
+        assert!(!output.contains("⚠️") && !output.contains("ℹ️"));
+        assert_eq!(Some(Severity::Deny), severity);
+
+        let (output, severity) = rendered(&[Decision::Info, Decision::Warn]);
+        assert!(output.contains("unknown: resource \"aws_db_instance\" \"main\" ... ⚠️"));
+        assert!(!output.contains("❌") && !output.contains("ℹ️"));
+        assert_eq!(Some(Severity::Warn), severity);
+
+        let (output, severity) = rendered(&[Decision::Info]);
+        assert!(output.contains("unknown: resource \"aws_db_instance\" \"main\" ... ℹ️"));
+        assert!(!output.contains("❌") && !output.contains("⚠️"));
+        assert_eq!(Some(Severity::Info), severity);
+    }
+
+    #[test]
+    fn an_allow_settles_the_block() {
+        let (output, severity) = rendered(&[
+            Decision::Deny,
+            Decision::Warn,
+            Decision::Info,
+            Decision::Allow,
+        ]);
+
+        assert!(output.contains("unknown ... ✅"));
+        assert!(!output.contains("❌") && !output.contains("⚠️") && !output.contains("ℹ️"));
+        assert_eq!(None, severity);
+    }
+
+    #[test]
+    fn reads_severities_from_the_command_line() {
+        assert_eq!(Ok(Severity::Warn), "warn".parse());
+        assert_eq!(Ok(Severity::Info), "info".parse());
+        assert!("error".parse::<Severity>().is_err());
     }
 }
@@ -2,7 +2,15 @@ use serde::Deserialize;
 use std::path::PathBuf;
 
 #[derive(Deserialize, Debug)]
-struct Children {}
+struct Children {
+    types: Vec<NodeType>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NodeType {
+    #[serde(rename = "type")]
+    kind: String,
+}
 
 #[derive(Deserialize, Debug)]
 struct Node {
@@ -40,6 +48,15 @@ fn main() {
         .collect::<Vec<String>>()
         .join("\n");
 
+    // Everything that can appear directly in a file: `resource`, `data`, `module`...
+    let top_level_blocks = types
+        .iter()
+        .filter(|n| n.kind == "configuration")
+        .flat_map(|n| n.children.iter().flat_map(|c| c.types.iter()))
+        .map(|t| format!("\"{}\" => true,", t.kind))
+        .collect::<Vec<String>>()
+        .join("\n");
+
     let rust_code = format!(
         r#"
         pub fn is_container(kind: &str) -> bool {{
@@ -48,8 +65,16 @@ fn main() {
                 _ => false,
             }}
         }}
+
+        pub fn is_top_level_block(kind: &str) -> bool {{
+            match kind {{
+                {top_level_blocks}
+                _ => false,
+            }}
+        }}
         "#,
-        containers = containers
+        containers = containers,
+        top_level_blocks = top_level_blocks
     );
 
     let out_dir = std::env::var_os("OUT_DIR").unwrap();
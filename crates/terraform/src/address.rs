@@ -0,0 +1,133 @@
+use super::BackingData;
+use std::fmt;
+use tree_sitter::Node;
+
+/// The kinds of blocks terraform gives an address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// `aws_db_instance.main`
+    Managed,
+    /// `data.aws_caller_identity.current`
+    Data,
+    /// `module.warehouse`
+    Module,
+}
+
+/// Blocks using `count` or `for_each` stand for several instances
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repetition {
+    Count,
+    ForEach,
+    /// One of the instances, as found in plans: the `0` or `"eu-west-1"` of the index
+    Instance(String),
+}
+
+/// What terraform calls a block in plans and state,
+/// e.g. `module.warehouse.aws_db_instance.main`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    /// The names of the module calls leading to the file, outermost first
+    pub module_path: Vec<String>,
+    pub mode: Mode,
+    /// The `aws_db_instance` of resources and data sources
+    pub block_type: Option<String>,
+    pub name: String,
+    pub repetition: Option<Repetition>,
+}
+
+impl Address {
+    /// The address of a `resource`, `data` or `module` block, other blocks don't have one
+    pub fn of(block: Node, terraform: &BackingData) -> Option<Address> {
+        let mode = match block.kind() {
+            "resource" => Mode::Managed,
+            "data" => Mode::Data,
+            "module" => Mode::Module,
+            _ => return None,
+        };
+
+        let mut labels = super::block_labels(&block, &terraform.input);
+        let name = labels.pop()?;
+        let block_type = labels.pop();
+
+        let body = super::named_children(&block).pop()?;
+        let repetition = if let Some(key) = &terraform.instance_key {
+            Some(Repetition::Instance(key.clone()))
+        } else if super::attribute(&body, "count", &terraform.input).is_some() {
+            Some(Repetition::Count)
+        } else if super::attribute(&body, "for_each", &terraform.input).is_some() {
+            Some(Repetition::ForEach)
+        } else {
+            None
+        };
+
+        Some(Address {
+            module_path: terraform.module_path.clone(),
+            mode,
+            block_type,
+            name,
+            repetition,
+        })
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for module in &self.module_path {
+            write!(f, "module.{}.", module)?;
+        }
+
+        match (self.mode, &self.block_type) {
+            (Mode::Module, _) => write!(f, "module.{}", self.name)?,
+            (Mode::Data, Some(block_type)) => write!(f, "data.{}.{}", block_type, self.name)?,
+            (_, Some(block_type)) => write!(f, "{}.{}", block_type, self.name)?,
+            (_, None) => write!(f, "{}", self.name)?,
+        }
+
+        match &self.repetition {
+            Some(Repetition::Count) => write!(f, "[count.index]"),
+            Some(Repetition::ForEach) => write!(f, "[each.key]"),
+            Some(Repetition::Instance(key)) => write!(f, "[{}]", key),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn prints_addresses_the_way_terraform_does() {
+        let address = Address {
+            module_path: vec!["warehouse".into()],
+            mode: Mode::Managed,
+            block_type: Some("aws_db_instance".into()),
+            name: "main".into(),
+            repetition: None,
+        };
+
+        assert_eq!("module.warehouse.aws_db_instance.main", address.to_string());
+    }
+
+    #[test]
+    fn marks_blocks_with_several_instances() {
+        let mut backing_data = crate::parse_text(
+            r#"
+data "aws_subnet" "private" {
+  for_each = var.subnet_ids
+  id       = each.value
+}
+"#,
+        );
+        backing_data.module_path = vec!["network".into(), "subnets".into()];
+
+        let data = crate::top_level_blocks(backing_data.root()).remove(0);
+        let address = Address::of(data, &backing_data).expect("data sources have an address");
+
+        assert_eq!(
+            "module.network.module.subnets.data.aws_subnet.private[each.key]",
+            address.to_string()
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use super::BackingData;
+use std::fmt;
+use tree_sitter::Node;
+
+/// A place where the parser had to give up on part of a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Starting at 1, as editors count them
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// A file that could only be parsed in parts. Matching rules against what
+/// was parsed would silently skip whatever is broken, so it isn't checked.
+#[derive(Debug, Clone)]
+pub struct SyntaxErrors {
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// The `ERROR` and `MISSING` nodes of a file, in the order they appear in
+pub fn diagnostics(terraform: &BackingData) -> Vec<Diagnostic> {
+    let mut found = Vec::new();
+    collect(terraform.root(), terraform, &mut found);
+    found
+}
+
+fn collect(node: Node, terraform: &BackingData, found: &mut Vec<Diagnostic>) {
+    if !node.has_error() {
+        return;
+    }
+
+    let message = if node.is_missing() {
+        Some(format!("missing `{}`", node.kind()))
+    } else if node.is_error() {
+        let text = terraform.text(node);
+        let first_line = text.lines().next().unwrap_or_default().trim();
+        Some(format!("unexpected `{}`", first_line))
+    } else {
+        None
+    };
+
+    if let Some(message) = message {
+        let position = node.start_position();
+        found.push(Diagnostic {
+            line: position.row + 1,
+            column: position.column + 1,
+            message,
+        });
+        // whatever is inside of an error is part of the same mistake
+        if node.is_error() {
+            return;
+        }
+    }
+
+    for child in node.children(&mut node.walk()) {
+        collect(child, terraform, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn unclosed_blocks_are_reported() {
+        let backing_data = crate::parse_text(
+            r#"
+resource "aws_db_instance" "main" {
+  engine = "postgres"
+"#,
+        );
+
+        assert!(!diagnostics(&backing_data).is_empty());
+    }
+
+    #[test]
+    fn well_formed_files_have_none() {
+        let backing_data = crate::parse_text(
+            r#"
+resource "aws_db_instance" "main" {
+  engine = "postgres"
+}
+"#,
+        );
+
+        assert_eq!(Vec::<Diagnostic>::new(), diagnostics(&backing_data));
+    }
+}
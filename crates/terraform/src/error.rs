@@ -0,0 +1,79 @@
+use super::diagnostic::SyntaxErrors;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Everything that can go wrong reading terraform, plans and state
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read
+    Io { path: String, source: io::Error },
+    /// A plan, state or `.tf.json` file that is not JSON
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+    /// A state file written by a terraform we don't understand
+    UnsupportedState { path: String, version: Option<u64> },
+    /// A `.tf` file that could only be parsed in parts
+    Syntax(SyntaxErrors),
+    /// A query tree-sitter did not accept
+    Query(String),
+}
+
+impl Error {
+    pub(crate) fn io(path: &Path, source: io::Error) -> Error {
+        Error::Io {
+            path: path.to_string_lossy().into(),
+            source,
+        }
+    }
+
+    pub(crate) fn json(path: &Path, source: serde_json::Error) -> Error {
+        Error::Json {
+            path: path.to_string_lossy().into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: could not be read: {}", path, source),
+            Error::Json { path, source } => write!(f, "{}: is not valid json: {}", path, source),
+            Error::UnsupportedState {
+                path,
+                version: Some(version),
+            } => write!(
+                f,
+                "{}: state version {} is not supported, only version 4 is",
+                path, version
+            ),
+            Error::UnsupportedState {
+                path,
+                version: None,
+            } => write!(f, "{}: is not a terraform state file", path),
+            Error::Syntax(errors) => {
+                write!(f, "{}: could not be parsed", errors.path)?;
+                for diagnostic in &errors.diagnostics {
+                    write!(f, "\n  {}", diagnostic)?;
+                }
+                Ok(())
+            }
+            Error::Query(message) => {
+                write!(f, "the rule could not be turned into a query: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use super::scope::Scope;
+use super::value::{unquote, Value};
+use std::collections::BTreeMap;
+use tree_sitter::Node;
+
+/// Computes the value of an expression as far as it is known without running terraform.
+///
+/// Binary operations are not wrapped in a node of their own, so `2 * 3` are three
+/// siblings and the expression is everything from `node` up to the last operand.
+/// Anything that can't be computed becomes a `Value::Expression` of its source text.
+pub fn evaluate(node: Node, scope: &Scope, source: &str) -> Value {
+    if continues_expression(node) {
+        return Value::Expression(text(node, source).into());
+    }
+
+    let mut operands = vec![operand(node, scope, source)];
+    let mut operators = Vec::new();
+    let mut last = node;
+    while let Some((operator, next)) = next_operation(last) {
+        operators.push(operator.kind());
+        operands.push(operand(next, scope, source));
+        last = next;
+    }
+
+    // a lone reference stays a reference when its value isn't known
+    if operators.is_empty() {
+        return operands
+            .pop()
+            .unwrap_or_else(|| Value::Expression(text(node, source).into()));
+    }
+
+    match reduce(operands, operators) {
+        Some(value) => value,
+        None => Value::Expression(source[node.start_byte()..last.end_byte()].into()),
+    }
+}
+
+/// Operators by how tightly they bind, as in the `_binary` rule of the grammar
+fn precedence(operator: &str) -> Option<u8> {
+    match operator {
+        "multiplication" | "division" => Some(6),
+        "addition" | "substraction" => Some(5),
+        "eq" | "gt" | "gt_eq" | "lt" | "lt_eq" => Some(4),
+        "&&" => Some(2),
+        "||" => Some(1),
+        "!" => Some(0),
+        _ => None,
+    }
+}
+
+fn is_operator(node: Option<Node>) -> bool {
+    matches!(node, Some(node) if precedence(node.kind()).is_some())
+}
+
+fn is_unary(node: Option<Node>) -> bool {
+    matches!(node, Some(node) if !node.is_named() && matches!(node.kind(), "-" | "+"))
+}
+
+/// Whether the node is the right hand side of an operator rather than the start of an expression
+fn continues_expression(node: Node) -> bool {
+    let previous = node.prev_sibling();
+    is_operator(previous)
+        || (is_unary(previous) && is_operator(previous.and_then(|p| p.prev_sibling())))
+}
+
+fn next_operation(node: Node) -> Option<(Node, Node)> {
+    let operator = node.next_sibling().filter(|n| is_operator(Some(*n)))?;
+    let mut operand = operator.next_sibling()?;
+    if is_unary(Some(operand)) {
+        operand = operand.next_sibling()?;
+    }
+    Some((operator, operand))
+}
+
+/// A single term of an expression, including a `-` or `+` in front of it
+fn operand(node: Node, scope: &Scope, source: &str) -> Value {
+    let value =
+        term(node, scope, source).unwrap_or_else(|| Value::Expression(text(node, source).into()));
+    match node.prev_sibling() {
+        Some(unary) if unary.kind() == "-" && is_unary(Some(unary)) => match number(&value) {
+            Some(n) => Value::Number(-n),
+            None => Value::Expression(format!("-{}", text(node, source))),
+        },
+        _ => value,
+    }
+}
+
+/// Applies the operators with the highest precedence first, from left to right
+fn reduce(operands: Vec<Value>, mut operators: Vec<&str>) -> Option<Value> {
+    let mut operands: Vec<Option<Value>> = operands.into_iter().map(Some).collect();
+    for level in &[6, 5, 4, 2, 1, 0] {
+        let mut idx = 0;
+        while idx < operators.len() {
+            if precedence(operators[idx]) != Some(*level) {
+                idx += 1;
+                continue;
+            }
+            let operator = operators.remove(idx);
+            let right = operands.remove(idx + 1);
+            let left = operands[idx].take();
+            operands[idx] = apply(operator, left?, right?);
+        }
+    }
+
+    operands.pop()?
+}
+
+fn apply(operator: &str, left: Value, right: Value) -> Option<Value> {
+    if !is_known(&left) || !is_known(&right) {
+        return None;
+    }
+
+    let numbers = || Some((number(&left)?, number(&right)?));
+    let booleans = || Some((boolean(&left)?, boolean(&right)?));
+
+    match operator {
+        "multiplication" => numbers().map(|(l, r)| Value::Number(l * r)),
+        "division" => numbers().map(|(l, r)| Value::Number(l / r)),
+        "addition" => numbers().map(|(l, r)| Value::Number(l + r)),
+        "substraction" => numbers().map(|(l, r)| Value::Number(l - r)),
+        "eq" => Some(Value::Bool(left.equivalent(&right))),
+        "gt" => numbers().map(|(l, r)| Value::Bool(l > r)),
+        "gt_eq" => numbers().map(|(l, r)| Value::Bool(l >= r)),
+        "lt" => numbers().map(|(l, r)| Value::Bool(l < r)),
+        "lt_eq" => numbers().map(|(l, r)| Value::Bool(l <= r)),
+        "&&" => booleans().map(|(l, r)| Value::Bool(l && r)),
+        "||" => booleans().map(|(l, r)| Value::Bool(l || r)),
+        _ => None,
+    }
+}
+
+fn term(node: Node, scope: &Scope, source: &str) -> Option<Value> {
+    let value = match node.kind() {
+        "value_or_query" | "fn_param" | "comparison" => evaluate(
+            super::named_children(&node).into_iter().next()?,
+            scope,
+            source,
+        ),
+        "interpolation_string" => interpolation(node, scope, source)?,
+        "function" => function(node, scope, source)?,
+        "list" => {
+            if super::named_children(&node)
+                .iter()
+                .any(|child| child.kind() == "for_comprehension")
+            {
+                return None;
+            }
+            Value::List(
+                starts(node)
+                    .into_iter()
+                    .map(|element| evaluate(element, scope, source))
+                    .collect(),
+            )
+        }
+        "map" => Value::Map(
+            super::named_children(&node)
+                .into_iter()
+                .filter_map(|entry| {
+                    let children = super::named_children(&entry);
+                    let (key, value) = (children.first()?, children.last()?);
+                    if key == value {
+                        return None;
+                    }
+                    let key = match Value::from_node(*key, source) {
+                        Value::String(key) => key,
+                        other => other.to_json(),
+                    };
+                    Some((key, evaluate(*value, scope, source)))
+                })
+                .collect(),
+        ),
+        "ternary" => {
+            let children: Vec<Node> = node.children(&mut node.walk()).collect();
+            let after = |token: &str| {
+                children
+                    .iter()
+                    .skip_while(|child| child.kind() != token)
+                    .find(|child| child.is_named())
+                    .copied()
+            };
+            let condition = boolean(&evaluate(*children.first()?, scope, source))?;
+            let branch = if condition { after("?")? } else { after(":")? };
+            evaluate(branch, scope, source)
+        }
+        _ => scope.resolve(Value::from_node(node, source)),
+    };
+
+    Some(value)
+}
+
+/// The named children that start an expression of their own, e.g. the elements of a list
+fn starts(node: Node) -> Vec<Node> {
+    super::named_children(&node)
+        .into_iter()
+        .filter(|child| !is_operator(Some(*child)) && !continues_expression(*child))
+        .collect()
+}
+
+fn interpolation(node: Node, scope: &Scope, source: &str) -> Option<Value> {
+    let segments = super::named_children(&node);
+
+    // `"${var.count}"` keeps the type of what is inside of it
+    if let [substitution] = segments.as_slice() {
+        if substitution.kind() == "interpolation_substitution" {
+            return Some(evaluate(
+                super::named_children(substitution).into_iter().next()?,
+                scope,
+                source,
+            ));
+        }
+    }
+
+    let mut result = String::new();
+    for segment in segments {
+        if segment.kind() == "interpolation_substitution" {
+            let inner = super::named_children(&segment).into_iter().next()?;
+            result.push_str(&as_text(&evaluate(inner, scope, source))?);
+        } else {
+            result.push_str(&unquote(text(segment, source)));
+        }
+    }
+
+    Some(Value::String(result))
+}
+
+fn function(node: Node, scope: &Scope, source: &str) -> Option<Value> {
+    let arguments: Vec<Value> = super::named_children(&node)
+        .into_iter()
+        .map(|param| evaluate(param, scope, source))
+        .collect();
+    let name = super::function_name(&node)?;
+
+    // as in `Value::from_node`, the document is compared rather than its encoding,
+    // references in it and all
+    if let ("jsonencode", [document]) = (name, arguments.as_slice()) {
+        return Some(document.clone());
+    }
+    if !arguments.iter().all(is_known) {
+        return None;
+    }
+
+    match (name, arguments.as_slice()) {
+        ("format", [Value::String(spec), rest @ ..]) => format(spec, rest).map(Value::String),
+        ("join", [Value::String(separator), lists @ ..]) => {
+            let mut parts = Vec::new();
+            for list in lists {
+                match list {
+                    Value::List(items) => {
+                        for item in items {
+                            parts.push(as_text(item)?);
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+            Some(Value::String(parts.join(separator)))
+        }
+        ("concat", lists) => {
+            let mut items = Vec::new();
+            for list in lists {
+                match list {
+                    Value::List(list) => items.extend(list.iter().cloned()),
+                    _ => return None,
+                }
+            }
+            Some(Value::List(items))
+        }
+        ("merge", maps) => {
+            let mut merged = BTreeMap::new();
+            for map in maps {
+                match map {
+                    Value::Map(entries) => merged.extend(entries.clone()),
+                    _ => return None,
+                }
+            }
+            Some(Value::Map(merged))
+        }
+        ("lookup", [Value::Map(entries), key, rest @ ..]) => entries
+            .get(&as_text(key)?)
+            .or_else(|| rest.first())
+            .cloned(),
+        ("lower", [value]) => Some(Value::String(as_text(value)?.to_lowercase())),
+        ("upper", [value]) => Some(Value::String(as_text(value)?.to_uppercase())),
+        ("tostring", [value]) => Some(Value::String(as_text(value)?)),
+        ("length", [Value::List(items)]) => Some(Value::Number(items.len() as f64)),
+        ("length", [Value::Map(entries)]) => Some(Value::Number(entries.len() as f64)),
+        ("length", [Value::String(s)]) => Some(Value::Number(s.chars().count() as f64)),
+        ("toset", [Value::List(items)]) => {
+            let mut set: Vec<Value> = Vec::new();
+            for item in items {
+                if !set.contains(item) {
+                    set.push(item.clone());
+                }
+            }
+            Some(Value::List(set))
+        }
+        ("replace", [value, Value::String(search), Value::String(replacement)])
+            if !search.starts_with('/') =>
+        {
+            Some(Value::String(as_text(value)?.replace(search, replacement)))
+        }
+        ("cidrsubnet", [Value::String(prefix), newbits, netnum]) => {
+            cidrsubnet(prefix, number(newbits)?, number(netnum)?).map(Value::String)
+        }
+        _ => None,
+    }
+}
+
+/// The `%s`, `%d` and `%v` verbs of terraform's `format`
+fn format(spec: &str, arguments: &[Value]) -> Option<String> {
+    let mut arguments = arguments.iter();
+    let mut result = String::new();
+    let mut chars = spec.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '%' => result.push('%'),
+            's' | 'v' => result.push_str(&as_text(arguments.next()?)?),
+            'd' => {
+                let n = number(arguments.next()?)?;
+                if n.fract() != 0.0 {
+                    return None;
+                }
+                result.push_str(&(n as i64).to_string());
+            }
+            _ => return None,
+        }
+    }
+
+    Some(result)
+}
+
+/// `cidrsubnet("10.0.0.0/16", 8, 2)` is `"10.0.2.0/24"`, for IPv4 prefixes
+fn cidrsubnet(prefix: &str, newbits: f64, netnum: f64) -> Option<String> {
+    let (address, length) = prefix.split_once('/')?;
+    let length: u32 = length.parse().ok()?;
+    let octets: Vec<u32> = address
+        .split('.')
+        .map(|octet| octet.parse::<u8>().ok().map(u32::from))
+        .collect::<Option<_>>()?;
+    if octets.len() != 4 {
+        return None;
+    }
+
+    let new_length = length + newbits as u32;
+    if new_length > 32 || netnum < 0.0 || netnum >= 2f64.powi(newbits as i32) {
+        return None;
+    }
+
+    let address = octets
+        .iter()
+        .fold(0u64, |acc, octet| acc << 8 | *octet as u64);
+    let mask = (u64::from(u32::MAX) << (32 - length)) & u64::from(u32::MAX);
+    let network = (address & mask) | ((netnum as u64) << (32 - new_length));
+
+    Some(format!(
+        "{}.{}.{}.{}/{}",
+        network >> 24 & 0xff,
+        network >> 16 & 0xff,
+        network >> 8 & 0xff,
+        network & 0xff,
+        new_length
+    ))
+}
+
+fn is_known(value: &Value) -> bool {
+    match value {
+        Value::Reference(_) | Value::Expression(_) => false,
+        Value::List(items) => items.iter().all(is_known),
+        Value::Map(entries) => entries.values().all(is_known),
+        _ => true,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => Some(*n),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn boolean(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) if s == "true" => Some(true),
+        Value::String(s) if s == "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// How a value looks when it ends up in a string
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => Some((*n as i64).to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn text<'a>(node: Node, source: &'a str) -> &'a str {
+    node.utf8_text(source.as_bytes()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn local(input: &str, name: &str) -> Value {
+        let backing_data = crate::parse_text(input);
+        let locals = crate::top_level_blocks(backing_data.root()).pop().unwrap();
+        let body = crate::named_children(&locals).pop().unwrap();
+        let node = crate::attribute(&body, name, &backing_data.input).unwrap();
+
+        evaluate(node, &Scope::default(), &backing_data.input)
+    }
+
+    #[test]
+    fn applies_operators_by_precedence() {
+        let input = r#"
+locals {
+  size    = 2 + 3 * 4
+  enabled = 10 > 3 && "a" == "a"
+  unknown = var.size * 2
+}
+"#;
+
+        assert_eq!(Value::Number(14.0), local(input, "size"));
+        assert_eq!(Value::Bool(true), local(input, "enabled"));
+        assert_eq!(
+            Value::Expression("var.size * 2".into()),
+            local(input, "unknown")
+        );
+    }
+
+    #[test]
+    fn calls_pure_functions() {
+        let input = r#"
+locals {
+  name   = format("%s-%d", upper("db"), 2)
+  joined = join(",", concat(["a"], ["b", "c"]))
+  found  = lookup({ env = "prod" }, "env", "dev")
+  subnet = cidrsubnet("10.0.0.0/16", 8, 2)
+  label  = "${lower("PROD")}-db"
+}
+"#;
+
+        assert_eq!(Value::String("DB-2".into()), local(input, "name"));
+        assert_eq!(Value::String("a,b,c".into()), local(input, "joined"));
+        assert_eq!(Value::String("prod".into()), local(input, "found"));
+        assert_eq!(Value::String("10.0.2.0/24".into()), local(input, "subnet"));
+        assert_eq!(Value::String("prod-db".into()), local(input, "label"));
+    }
+
+    #[test]
+    fn computes_subnets() {
+        assert_eq!(
+            Some("172.16.16.0/20".to_string()),
+            cidrsubnet("172.16.0.0/12", 8.0, 1.0)
+        );
+        assert_eq!(None, cidrsubnet("10.0.0.0/30", 4.0, 1.0));
+    }
+
+    #[test]
+    fn formats_strings_and_numbers() {
+        assert_eq!(
+            Some("db-2 (100%)".to_string()),
+            format(
+                "%s-%d (100%%)",
+                &[Value::String("db".into()), Value::Number(2.0)]
+            )
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use super::value::Value;
+use super::BackingData;
+use tree_sitter::Node;
+
+/// The parts of an IAM policy statement we reason about
+#[derive(Debug, Default, PartialEq)]
+pub struct Statement {
+    pub allow: bool,
+    pub actions: Vec<String>,
+    pub not_actions: Vec<String>,
+    pub resources: Vec<String>,
+    pub has_condition: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Policy {
+    pub statements: Vec<Statement>,
+}
+
+/// The vetted checks rule documents can use as `$(iam_...)` placeholders.
+/// Each of them holds when the policy is considered safe.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Check {
+    /// No `Allow` of `*` actions on `*` resources
+    NoWildcardAdmin,
+    /// No `Allow` of `*` or `service:*` actions
+    NoWildcardAction,
+    /// No `Allow` on `*` resources
+    NoWildcardResource,
+    /// No `Allow` combined with `NotAction`
+    NoNotActionAllow,
+    /// Every `Allow` is restricted by a `Condition`
+    RequiresCondition,
+}
+
+impl Check {
+    pub fn from_name(name: &str) -> Option<Check> {
+        match name {
+            "iam_no_wildcard_admin" => Some(Check::NoWildcardAdmin),
+            "iam_no_wildcard_action" => Some(Check::NoWildcardAction),
+            "iam_no_wildcard_resource" => Some(Check::NoWildcardResource),
+            "iam_no_not_action_allow" => Some(Check::NoNotActionAllow),
+            "iam_requires_condition" => Some(Check::RequiresCondition),
+            _ => None,
+        }
+    }
+
+    pub fn passes(self, policy: &Policy) -> bool {
+        policy
+            .statements
+            .iter()
+            .filter(|statement| statement.allow)
+            .all(|statement| match self {
+                Check::NoWildcardAdmin => {
+                    !(statement.actions.iter().any(|a| a == "*" || a == "*:*")
+                        && statement.resources.iter().any(|r| r == "*"))
+                }
+                Check::NoWildcardAction => !statement
+                    .actions
+                    .iter()
+                    .any(|a| a == "*" || a.ends_with(":*")),
+                Check::NoWildcardResource => !statement.resources.iter().any(|r| r == "*"),
+                Check::NoNotActionAllow => statement.not_actions.is_empty(),
+                Check::RequiresCondition => statement.has_condition,
+            })
+    }
+}
+
+impl Policy {
+    /// Reads a policy from wherever rules can point at one:
+    /// the body of an `aws_iam_policy_document` data source, a `jsonencode(...)`,
+    /// heredoc or string holding the JSON, or a reference to such a data source
+    /// in the same file.
+    pub fn from_node(node: Node, terraform: &BackingData) -> Option<Policy> {
+        match node.kind() {
+            "block" | "map"
+                if !super::nested_blocks(&node, "statement", &terraform.input).is_empty() =>
+            {
+                Some(Policy::from_policy_document(node, &terraform.input))
+            }
+            "reference" => {
+                let name = policy_document_name(terraform.text(node))?;
+                let document =
+                    super::top_level_blocks(terraform.root())
+                        .into_iter()
+                        .find(|block| {
+                            block.kind() == "data"
+                                && super::block_labels(block, &terraform.input)
+                                    == ["aws_iam_policy_document", name]
+                        })?;
+                let body = super::named_children(&document).pop()?;
+
+                Some(Policy::from_policy_document(body, &terraform.input))
+            }
+            _ => Policy::from_value(&terraform.value(node)),
+        }
+    }
+
+    /// Reads a JSON policy document
+    pub fn from_value(value: &Value) -> Option<Policy> {
+        let document = match value {
+            Value::String(text) => Value::from_json(text)?,
+            other => other.clone(),
+        };
+
+        let statements = match &document {
+            Value::Map(entries) => match entries.get("Statement")? {
+                Value::List(statements) => statements.iter().collect(),
+                single => vec![single],
+            },
+            _ => return None,
+        };
+
+        Some(Policy {
+            statements: statements
+                .into_iter()
+                .filter_map(|statement| match statement {
+                    Value::Map(entries) => Some(Statement {
+                        allow: entries.get("Effect") != Some(&Value::String("Deny".into())),
+                        actions: strings(entries.get("Action")),
+                        not_actions: strings(entries.get("NotAction")),
+                        resources: strings(entries.get("Resource")),
+                        has_condition: entries.contains_key("Condition"),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        })
+    }
+
+    /// Reads the `statement` blocks of an `aws_iam_policy_document` data source
+    pub fn from_policy_document(block: Node, source: &str) -> Policy {
+        let statements = super::nested_blocks(&block, "statement", source)
+            .into_iter()
+            .map(|statement| {
+                let value = |name: &str| {
+                    super::attribute(&statement, name, source).map(|n| Value::from_node(n, source))
+                };
+
+                Statement {
+                    allow: value("effect") != Some(Value::String("Deny".into())),
+                    actions: strings(value("actions").as_ref()),
+                    not_actions: strings(value("not_actions").as_ref()),
+                    resources: strings(value("resources").as_ref()),
+                    has_condition: !super::nested_blocks(&statement, "condition", source)
+                        .is_empty(),
+                }
+            })
+            .collect();
+
+        Policy { statements }
+    }
+}
+
+/// `data.aws_iam_policy_document.<name>.json` gives us `<name>`
+fn policy_document_name(reference: &str) -> Option<&str> {
+    let name = reference
+        .strip_prefix("data.aws_iam_policy_document.")?
+        .strip_suffix(".json")?;
+
+    Some(name)
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::List(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn policy(json: &str) -> Policy {
+        Policy::from_value(&Value::from_json(json).expect("invalid json in test"))
+            .expect("not a policy")
+    }
+
+    #[test]
+    fn reads_single_statements_and_actions() {
+        let p = policy(r#"{"Statement": {"Effect": "Allow", "Action": "s3:*", "Resource": "*"}}"#);
+
+        assert_eq!(
+            Policy {
+                statements: vec![Statement {
+                    allow: true,
+                    actions: vec!["s3:*".into()],
+                    not_actions: vec![],
+                    resources: vec!["*".into()],
+                    has_condition: false,
+                }]
+            },
+            p
+        );
+    }
+
+    #[test]
+    fn flags_wildcard_admin_policies() {
+        let admin =
+            policy(r#"{"Statement": [{"Effect": "Allow", "Action": ["*"], "Resource": "*"}]}"#);
+        let denied =
+            policy(r#"{"Statement": [{"Effect": "Deny", "Action": "*", "Resource": "*"}]}"#);
+
+        assert!(!Check::NoWildcardAdmin.passes(&admin));
+        assert!(Check::NoWildcardAdmin.passes(&denied));
+    }
+
+    #[test]
+    fn flags_not_action_with_allow() {
+        let p = policy(
+            r#"{"Statement": [{"Effect": "Allow", "NotAction": "iam:*", "Resource": "*"}]}"#,
+        );
+
+        assert!(!Check::NoNotActionAllow.passes(&p));
+        assert!(Check::NoWildcardAction.passes(&p));
+    }
+
+    #[test]
+    fn reads_policy_document_data_sources() {
+        let backing_data = crate::parse_text(
+            r#"
+data "aws_iam_policy_document" "read" {
+  statement {
+    actions   = ["s3:GetObject"]
+    resources = ["*"]
+
+    condition {
+      test     = "StringEquals"
+      variable = "aws:PrincipalTag/team"
+      values   = ["data"]
+    }
+  }
+}
+"#,
+        );
+
+        let data = crate::top_level_blocks(backing_data.root()).remove(0);
+        let body = crate::named_children(&data).pop().unwrap();
+        let p = Policy::from_policy_document(body, &backing_data.input);
+
+        assert!(Check::RequiresCondition.passes(&p));
+        assert!(!Check::NoWildcardResource.passes(&p));
+    }
+}
@@ -0,0 +1,180 @@
+use super::diagnostic::{self, SyntaxErrors};
+use super::plan::{body, expression, template};
+use super::{BackingData, Error};
+use serde_json::{Map, Value as Json};
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+/// Reads a `.tf.json` file. Its blocks get written out in the native syntax,
+/// so rules match generated configuration the way they match `.tf` files.
+///
+/// Without the provider schemas objects can't be told apart from nested blocks:
+/// lists of objects become nested blocks and every other object a map.
+pub fn parse_json(path: PathBuf) -> Result<BackingData, Error> {
+    let input = read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+    let configuration: Json = serde_json::from_str(&input).map_err(|e| Error::json(&path, e))?;
+
+    parse_configuration(&configuration, path.to_string_lossy().into())
+}
+
+/// `${...}` templates are copied over as they are and may use syntax we can't parse,
+/// those files are not checked like broken `.tf` files.
+/// The lines and columns of their errors are those of the blocks written out.
+fn parse_configuration(configuration: &Json, path: String) -> Result<BackingData, Error> {
+    let mut backing_data = super::parse_text(&to_hcl(configuration));
+    backing_data.path = path;
+
+    let diagnostics = diagnostic::diagnostics(&backing_data);
+    if diagnostics.is_empty() {
+        Ok(backing_data)
+    } else {
+        Err(Error::Syntax(SyntaxErrors {
+            path: backing_data.path,
+            diagnostics,
+        }))
+    }
+}
+
+fn to_hcl(configuration: &Json) -> String {
+    let mut hcl = String::new();
+    for (kind, content) in objects(configuration).into_iter().flatten() {
+        match kind.as_str() {
+            // `resource.<type>.<name>`
+            "resource" | "data" => {
+                for (block_type, named) in objects(content).into_iter().flatten() {
+                    for (name, content) in objects(named).into_iter().flatten() {
+                        for values in objects(content) {
+                            let header = format!("{} \"{}\" \"{}\"", kind, block_type, name);
+                            block(&header, values, &mut hcl);
+                        }
+                    }
+                }
+            }
+            // `module.<name>`
+            "module" | "provider" | "output" => {
+                for (name, content) in objects(content).into_iter().flatten() {
+                    for values in objects(content) {
+                        block(&format!("{} \"{}\"", kind, name), values, &mut hcl);
+                    }
+                }
+            }
+            "variable" => {
+                for (name, content) in objects(content).into_iter().flatten() {
+                    for values in objects(content) {
+                        variable(name, values, &mut hcl);
+                    }
+                }
+            }
+            "locals" | "terraform" => {
+                for values in objects(content) {
+                    block(kind, values, &mut hcl);
+                }
+            }
+            _ => continue,
+        }
+    }
+    hcl
+}
+
+/// The JSON syntax allows a list of objects wherever a single object is expected
+fn objects(content: &Json) -> Vec<&Map<String, Json>> {
+    match content {
+        Json::Object(values) => vec![values],
+        Json::Array(items) => items.iter().filter_map(Json::as_object).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn block(header: &str, values: &Map<String, Json>, hcl: &mut String) {
+    hcl.push_str(&format!("{} {{\n", header));
+    body(values, 1, template, hcl);
+    hcl.push_str("}\n\n");
+}
+
+/// Variables only have a few settings, and their `type` is written as a string
+fn variable(name: &str, values: &Map<String, Json>, hcl: &mut String) {
+    hcl.push_str(&format!("variable \"{}\" {{\n", name));
+    for (key, value) in values {
+        let value = match (key.as_str(), value) {
+            ("type", Json::String(ty)) => ty
+                .trim_start_matches("${")
+                .trim_end_matches('}')
+                .to_string(),
+            ("description", _) | ("default", _) | ("sensitive", _) => {
+                expression(value, 1, template)
+            }
+            _ => continue,
+        };
+        hcl.push_str(&format!("  {} = {}\n", key, value));
+    }
+    hcl.push_str("}\n\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn writes_blocks_in_the_native_syntax() {
+        let configuration: Json = serde_json::from_str(
+            r#"{
+  "//": "generated, do not edit",
+  "variable": {
+    "engine": { "type": "string", "default": "postgres" }
+  },
+  "resource": {
+    "aws_db_instance": {
+      "main": {
+        "engine": "${var.engine}",
+        "tags": { "team": "data" }
+      }
+    }
+  },
+  "provider": {
+    "aws": [{ "region": "eu-west-1" }, { "alias": "us", "region": "us-east-1" }]
+  }
+}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            r#"provider "aws" {
+  region = "eu-west-1"
+}
+
+provider "aws" {
+  alias = "us"
+  region = "us-east-1"
+}
+
+resource "aws_db_instance" "main" {
+  engine = "${var.engine}"
+  tags = {
+    team = "data"
+  }
+}
+
+variable "engine" {
+  default = "postgres"
+  type = string
+}
+
+"#,
+            to_hcl(&configuration)
+        );
+    }
+
+    #[test]
+    fn templates_that_dont_parse_are_syntax_errors() {
+        let configuration: Json = serde_json::from_str(
+            r#"{ "resource": { "aws_s3_bucket": { "logs": { "bucket": "${var.name ?}" } } } }"#,
+        )
+        .unwrap();
+
+        match parse_configuration(&configuration, "main.tf.json".into()) {
+            Err(Error::Syntax(errors)) => assert_eq!("main.tf.json", errors.path),
+            other => panic!("expected a syntax error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -1,14 +1,33 @@
+use diagnostic::SyntaxErrors;
+pub use error::Error;
+use scope::Scope;
 use std::fs::read_to_string;
 use std::ops::Range;
 use std::path::PathBuf;
 use tree_sitter::{Language, Node, Parser, Query, Tree};
+use value::Value;
+
+pub mod address;
+pub mod diagnostic;
+mod error;
+pub mod evaluate;
+pub mod iam;
+pub mod json_syntax;
+pub mod module_source;
+pub mod module_tree;
+pub mod plan;
+pub mod reference;
+pub mod scope;
+pub mod state;
+pub mod value;
+pub mod version;
 
 extern "C" {
     fn tree_sitter_terraform() -> Language;
 }
 
-pub fn query(source: &str) -> Query {
-    Query::new(parser().language().unwrap(), source).expect("unworkable query")
+pub fn query(source: &str) -> Result<Query, Error> {
+    Query::new(parser().language().unwrap(), source).map_err(|e| Error::Query(format!("{:?}", e)))
 }
 
 pub fn parser() -> Parser {
@@ -27,6 +46,12 @@ pub struct BackingData {
     tree: Tree,
     pub input: String,
     pub path: String,
+    /// The module calls that lead to this file from a root module
+    pub module_path: Vec<String>,
+    /// What is known about the variables and locals of the module
+    pub scope: Scope,
+    /// The index of the resource instance this was read from, for plans
+    pub instance_key: Option<String>,
 }
 
 impl BackingData {
@@ -41,6 +66,12 @@ impl BackingData {
     pub fn root(&self) -> Node {
         self.tree.root_node()
     }
+
+    /// The value of a node with the variables and locals it refers to filled in
+    /// and the expressions that only depend on them computed
+    pub fn value(&self, n: Node) -> Value {
+        evaluate::evaluate(n, &self.scope, &self.input)
+    }
 }
 
 // temporary for testing?
@@ -52,16 +83,29 @@ pub fn parse_text(input: &str) -> BackingData {
         tree,
         input: input.to_string(),
         path: "unknown".into(),
+        module_path: Vec::new(),
+        scope: Scope::default(),
+        instance_key: None,
     }
 }
 
-pub fn parse(path: PathBuf) -> BackingData {
-    let input = read_to_string(&path).unwrap();
+/// Reads a `.tf` file, unless parts of it could not be parsed
+pub fn parse(path: PathBuf) -> Result<BackingData, Error> {
+    let input = read_to_string(&path).map_err(|e| Error::io(&path, e))?;
     let mut backing_data = parse_text(&input);
     let path = path.to_string_lossy().into();
 
     backing_data.path = path;
-    backing_data
+
+    let diagnostics = diagnostic::diagnostics(&backing_data);
+    if diagnostics.is_empty() {
+        Ok(backing_data)
+    } else {
+        Err(Error::Syntax(SyntaxErrors {
+            path: backing_data.path,
+            diagnostics,
+        }))
+    }
 }
 
 include!(concat!(env!("OUT_DIR"), "/is_container.rs"));
@@ -70,6 +114,76 @@ pub fn is_query(kind: &str) -> bool {
     kind == "query"
 }
 
+pub fn named_children<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    node.children(&mut node.walk())
+        .filter(|n| n.is_named())
+        .collect()
+}
+
+/// The `resource`, `data`, `module`... blocks of a file
+pub fn top_level_blocks(root: Node) -> Vec<Node> {
+    named_children(&root)
+}
+
+/// The quoted labels of a block, e.g. `["aws_db_instance", "main"]`
+pub fn block_labels(block: &Node, source: &str) -> Vec<String> {
+    named_children(block)
+        .into_iter()
+        .filter(|n| n.kind() != "block" && n.kind() != "variable_block")
+        .map(|n| value::unquote(n.utf8_text(source.as_bytes()).unwrap_or_default()))
+        .collect()
+}
+
+/// Every `name = ...` directly inside of a block
+pub fn attributes<'a>(block: &Node<'a>, source: &str) -> Vec<(String, Node<'a>)> {
+    named_children(block)
+        .into_iter()
+        .filter_map(|attribute| match named_children(&attribute).as_slice() {
+            [identifier, value, ..] if identifier.kind() == "identifier" => Some((
+                identifier
+                    .utf8_text(source.as_bytes())
+                    .unwrap_or_default()
+                    .to_string(),
+                *value,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The value of `name = ...` directly inside of a block
+pub fn attribute<'a>(block: &Node<'a>, name: &str, source: &str) -> Option<Node<'a>> {
+    attributes(block, source)
+        .into_iter()
+        .find(|(attribute, _)| attribute == name)
+        .map(|(_, value)| value)
+}
+
+/// The bodies of nested blocks such as `statement { ... }`
+pub fn nested_blocks<'a>(block: &Node<'a>, name: &str, source: &str) -> Vec<Node<'a>> {
+    named_children(block)
+        .into_iter()
+        .flat_map(|attribute| named_children(&attribute))
+        .filter(|named_map| named_map.kind() == "named_map")
+        .filter_map(|named_map| {
+            let children = named_children(&named_map);
+            let identifier = children.first()?;
+            if identifier.utf8_text(source.as_bytes()) != Ok(name) {
+                return None;
+            }
+            children.last().copied()
+        })
+        .collect()
+}
+
+/// The name of a function call such as `jsonencode` in `jsonencode({...})`
+pub fn function_name(node: &Node) -> Option<&'static str> {
+    if node.kind() != "function" {
+        return None;
+    }
+    node.child(0).map(|name| name.kind())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
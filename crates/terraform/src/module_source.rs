@@ -0,0 +1,258 @@
+use super::value::unquote;
+use super::version;
+
+/// Where terraform fetches the code of a `module` block from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// `./network` or `../shared/network`
+    Local,
+    /// `hashicorp/consul/aws` or `app.terraform.io/our-org/consul/aws`
+    Registry,
+    /// `git::https://...`, `git@github.com:...` or `github.com/...`
+    Git,
+    /// Anything else: plain URLs, buckets, mercurial...
+    Other,
+}
+
+/// The `source` attribute of a `module` block taken apart
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleSource {
+    pub kind: Kind,
+    pub host: Option<String>,
+    pub path: String,
+    /// The `?ref=` of a git source
+    pub reference: Option<String>,
+}
+
+const DEFAULT_REGISTRY: &str = "registry.terraform.io";
+
+impl ModuleSource {
+    pub fn parse(source: &str) -> ModuleSource {
+        if source.starts_with("./") || source.starts_with("../") {
+            return ModuleSource {
+                kind: Kind::Local,
+                host: None,
+                path: source.into(),
+                reference: None,
+            };
+        }
+
+        if let Some(git) = git_source(source) {
+            return git;
+        }
+
+        if let Some(registry) = registry_source(source) {
+            return registry;
+        }
+
+        ModuleSource {
+            kind: Kind::Other,
+            host: None,
+            path: source.into(),
+            reference: None,
+        }
+    }
+
+    /// The `our-org` of `our-org/consul/aws`
+    pub fn namespace(&self) -> Option<&str> {
+        match self.kind {
+            Kind::Registry => self.path.split('/').next(),
+            _ => None,
+        }
+    }
+}
+
+/// Handles the explicit `git::` prefix as well as the shorthands terraform
+/// knows for GitHub and Bitbucket
+fn git_source(source: &str) -> Option<ModuleSource> {
+    let (address, reference) = match source.find("?ref=") {
+        Some(idx) => {
+            let reference = source[idx + "?ref=".len()..].split('&').next();
+            (&source[..idx], reference.map(String::from))
+        }
+        None => (source, None),
+    };
+
+    let address = if let Some(address) = address.strip_prefix("git::") {
+        address
+    } else if address.starts_with("git@")
+        || address.starts_with("github.com/")
+        || address.starts_with("bitbucket.org/")
+    {
+        address
+    } else {
+        return None;
+    };
+
+    let without_scheme = match address.find("://") {
+        Some(idx) => &address[idx + "://".len()..],
+        None => address,
+    };
+    let without_user = match without_scheme.split_once('@') {
+        Some((_, rest)) => rest,
+        None => without_scheme,
+    };
+    // `git@github.com:org/repo.git` separates the host with a colon
+    let (host, path) = match without_user.find(['/', ':'].as_ref()) {
+        Some(idx) => (&without_user[..idx], &without_user[idx + 1..]),
+        None => (without_user, ""),
+    };
+
+    Some(ModuleSource {
+        kind: Kind::Git,
+        host: Some(host.into()),
+        path: path.into(),
+        reference,
+    })
+}
+
+/// `<namespace>/<name>/<provider>`, optionally prefixed by the host of a private registry
+fn registry_source(source: &str) -> Option<ModuleSource> {
+    let address = source.split("//").next()?;
+    let parts: Vec<&str> = address.split('/').collect();
+
+    let (host, path) = match parts.as_slice() {
+        [_, _, _] => (DEFAULT_REGISTRY, address),
+        [host, _, _, _] if host.contains('.') => (*host, &address[host.len() + 1..]),
+        _ => return None,
+    };
+
+    let is_name = |part: &&str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if !path.split('/').all(|part| is_name(&part)) {
+        return None;
+    }
+
+    Some(ModuleSource {
+        kind: Kind::Registry,
+        host: Some(host.into()),
+        path: path.into(),
+        reference: None,
+    })
+}
+
+/// Whether a git ref looks like a release tag such as `v1.2.0` rather than a branch
+fn is_version_tag(reference: &str) -> bool {
+    version::is_exact(reference.strip_prefix('v').unwrap_or(reference))
+}
+
+/// The checks rule documents can use as placeholders in `module` blocks
+#[derive(Debug, Clone, PartialEq)]
+pub enum Check {
+    /// `source = $(local_source)`
+    LocalSource,
+    /// `source = $(git_ref_is_tag)`: a git source pinned with `?ref=vX.Y.Z`
+    GitRefIsTag,
+    /// `source = $(git_host "git.example.com")`
+    GitHost(Vec<String>),
+    /// `source = $(registry_namespace "our-org")`
+    RegistryNamespace(Vec<String>),
+}
+
+impl Check {
+    pub fn from_name(name: &str, arguments: &[String]) -> Option<Check> {
+        let arguments = || arguments.iter().map(|arg| unquote(arg)).collect();
+
+        match name {
+            "local_source" => Some(Check::LocalSource),
+            "git_ref_is_tag" => Some(Check::GitRefIsTag),
+            "git_host" => Some(Check::GitHost(arguments())),
+            "registry_namespace" => Some(Check::RegistryNamespace(arguments())),
+            _ => None,
+        }
+    }
+
+    pub fn passes(&self, source: &str) -> bool {
+        let source = ModuleSource::parse(source);
+
+        match self {
+            Check::LocalSource => source.kind == Kind::Local,
+            Check::GitRefIsTag => {
+                source.kind == Kind::Git
+                    && matches!(&source.reference, Some(reference) if is_version_tag(reference))
+            }
+            Check::GitHost(hosts) => {
+                source.kind == Kind::Git
+                    && matches!(&source.host, Some(host) if hosts.contains(host))
+            }
+            Check::RegistryNamespace(namespaces) => {
+                matches!(source.namespace(), Some(namespace) if namespaces.iter().any(|n| n == namespace))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_git_sources() {
+        assert_eq!(
+            ModuleSource {
+                kind: Kind::Git,
+                host: Some("git.example.com".into()),
+                path: "platform/network.git//vpc".into(),
+                reference: Some("v1.2.0".into()),
+            },
+            ModuleSource::parse(
+                "git::https://git.example.com/platform/network.git//vpc?ref=v1.2.0"
+            )
+        );
+        assert_eq!(
+            ModuleSource {
+                kind: Kind::Git,
+                host: Some("github.com".into()),
+                path: "our-org/network.git".into(),
+                reference: None,
+            },
+            ModuleSource::parse("git@github.com:our-org/network.git")
+        );
+    }
+
+    #[test]
+    fn parses_registry_sources() {
+        let public = ModuleSource::parse("terraform-aws-modules/vpc/aws");
+        let private = ModuleSource::parse("app.terraform.io/our-org/vpc/aws");
+
+        assert_eq!(Kind::Registry, public.kind);
+        assert_eq!(Some("registry.terraform.io".into()), public.host);
+        assert_eq!(Some("terraform-aws-modules"), public.namespace());
+        assert_eq!(Some("app.terraform.io".into()), private.host);
+        assert_eq!(Some("our-org"), private.namespace());
+    }
+
+    #[test]
+    fn tells_local_sources_from_everything_else() {
+        assert_eq!(Kind::Local, ModuleSource::parse("../shared/network").kind);
+        assert_eq!(
+            Kind::Other,
+            ModuleSource::parse("https://example.com/network.zip").kind
+        );
+    }
+
+    #[test]
+    fn git_refs_need_to_be_version_tags() {
+        let check = Check::from_name("git_ref_is_tag", &[]).unwrap();
+
+        assert!(check.passes("git::https://git.example.com/network.git?ref=v1.2.0"));
+        assert!(!check.passes("git::https://git.example.com/network.git?ref=main"));
+        assert!(!check.passes("git::https://git.example.com/network.git"));
+    }
+
+    #[test]
+    fn checks_namespaces_and_hosts() {
+        let namespace = Check::from_name("registry_namespace", &["\"our-org\"".into()]).unwrap();
+        let host = Check::from_name("git_host", &["\"git.example.com\"".into()]).unwrap();
+
+        assert!(namespace.passes("our-org/vpc/aws"));
+        assert!(!namespace.passes("their-org/vpc/aws"));
+        assert!(host.passes("git::ssh://git@git.example.com/network.git"));
+        assert!(!host.passes("github.com/our-org/network"));
+    }
+}
@@ -0,0 +1,232 @@
+use super::module_source::{Kind, ModuleSource};
+use super::scope::Scope;
+use super::value::Value;
+use super::BackingData;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+
+/// A module as instantiated by its caller, e.g. the `warehouse` module as
+/// called by the `prod` root module, along with the modules it calls in turn
+#[derive(Debug)]
+pub struct ModuleTree<'a> {
+    pub directory: PathBuf,
+    /// The names of the module calls leading here, empty for a root module
+    pub module_path: Vec<String>,
+    pub files: Vec<&'a BackingData>,
+    pub children: Vec<ModuleTree<'a>>,
+}
+
+impl<'a> ModuleTree<'a> {
+    /// Every module in the tree, starting with the root
+    pub fn instances(&self) -> Vec<&ModuleTree<'a>> {
+        let mut instances = vec![self];
+        for child in &self.children {
+            instances.extend(child.instances());
+        }
+        instances
+    }
+
+    /// Every module in the tree along with what is known about its variables,
+    /// which for called modules depends on the arguments of the call
+    pub fn instances_with_scope(&self, scope: Scope) -> Vec<(&ModuleTree<'a>, Scope)> {
+        let mut instances = Vec::new();
+        for child in &self.children {
+            let name = child.module_path.last().map(String::as_str).unwrap_or("");
+            let called = scope.called(name, &self.files, &child.files);
+            instances.extend(child.instances_with_scope(called));
+        }
+        instances.insert(0, (self, scope));
+        instances
+    }
+}
+
+/// One tree for every root module, following `module` blocks with a local `source`
+pub fn build(files: &[BackingData]) -> Vec<ModuleTree<'_>> {
+    let modules = by_directory(files);
+
+    root_modules(files)
+        .into_keys()
+        .map(|directory| instantiate(&modules, directory, Vec::new(), &mut Vec::new()))
+        .collect()
+}
+
+fn instantiate<'a>(
+    modules: &BTreeMap<PathBuf, Vec<&'a BackingData>>,
+    directory: PathBuf,
+    module_path: Vec<String>,
+    ancestors: &mut Vec<PathBuf>,
+) -> ModuleTree<'a> {
+    let files = modules.get(&directory).cloned().unwrap_or_default();
+
+    ancestors.push(directory.clone());
+    let mut children = Vec::new();
+    for (name, called) in files.iter().flat_map(|file| local_calls(file)) {
+        // a module calling one of its callers would never end
+        if ancestors.contains(&called) {
+            continue;
+        }
+        let mut nested = module_path.clone();
+        nested.push(name);
+        children.push(instantiate(modules, called, nested, ancestors));
+    }
+    ancestors.pop();
+
+    ModuleTree {
+        directory,
+        module_path,
+        files,
+        children,
+    }
+}
+
+/// The files of every directory, which is what terraform considers a module
+pub fn by_directory(files: &[BackingData]) -> BTreeMap<PathBuf, Vec<&BackingData>> {
+    let mut modules: BTreeMap<PathBuf, Vec<&BackingData>> = BTreeMap::new();
+    for file in files {
+        modules.entry(directory_of(file)).or_default().push(file);
+    }
+    modules
+}
+
+/// The directories terraform gets run in: every directory with `.tf` files
+/// that is not called as a local module by another one, with the files in it
+pub fn root_modules(files: &[BackingData]) -> BTreeMap<PathBuf, Vec<&BackingData>> {
+    let mut modules = by_directory(files);
+
+    let called: BTreeSet<PathBuf> = files
+        .iter()
+        .flat_map(local_calls)
+        .map(|(_, directory)| directory)
+        .collect();
+
+    modules.retain(|directory, _| !called.contains(directory));
+    modules
+}
+
+/// The `module` blocks of a file with a local source: their name and the directory they call
+fn local_calls(file: &BackingData) -> Vec<(String, PathBuf)> {
+    super::top_level_blocks(file.root())
+        .into_iter()
+        .filter(|block| block.kind() == "module")
+        .filter_map(|block| {
+            let name = super::block_labels(&block, &file.input).pop()?;
+            let body = super::named_children(&block).pop()?;
+            let source = super::attribute(&body, "source", &file.input)?;
+            match Value::from_node(source, &file.input) {
+                Value::String(source) if ModuleSource::parse(&source).kind == Kind::Local => {
+                    Some((name, normalise(&directory_of(file).join(source))))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The directory of a file, which is the module it belongs to
+pub fn directory_of(file: &BackingData) -> PathBuf {
+    normalise(
+        Path::new(&file.path)
+            .parent()
+            .unwrap_or_else(|| Path::new("")),
+    )
+}
+
+/// Resolves `.` and `..` without touching the file system
+fn normalise(path: &Path) -> PathBuf {
+    let mut normalised = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalised.file_name().is_some() => {
+                normalised.pop();
+            }
+            other => normalised.push(other),
+        }
+    }
+    normalised
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn file(path: &str, input: &str) -> BackingData {
+        let mut backing_data = crate::parse_text(input);
+        backing_data.path = path.into();
+        backing_data
+    }
+
+    #[test]
+    fn modules_called_from_elsewhere_are_not_root_modules() {
+        let files = vec![
+            file(
+                "live/main.tf",
+                r#"
+module "network" {
+  source = "../modules/network"
+}
+"#,
+            ),
+            file("modules/network/main.tf", "locals {}"),
+        ];
+
+        let roots: Vec<PathBuf> = root_modules(&files).into_keys().collect();
+
+        assert_eq!(vec![PathBuf::from("live")], roots);
+    }
+
+    #[test]
+    fn shared_modules_are_instantiated_once_per_caller() {
+        let files = vec![
+            file(
+                "prod/main.tf",
+                r#"
+module "warehouse" {
+  source = "../modules/warehouse"
+}
+"#,
+            ),
+            file(
+                "staging/main.tf",
+                r#"
+module "warehouse" {
+  source = "../modules/warehouse"
+}
+"#,
+            ),
+            file(
+                "modules/warehouse/main.tf",
+                r#"
+module "storage" {
+  source = "./storage"
+}
+"#,
+            ),
+            file("modules/warehouse/storage/main.tf", "locals {}"),
+        ];
+
+        let trees = build(&files);
+        let prod: Vec<(PathBuf, Vec<String>)> = trees[0]
+            .instances()
+            .into_iter()
+            .map(|instance| (instance.directory.clone(), instance.module_path.clone()))
+            .collect();
+
+        assert_eq!(2, trees.len());
+        assert_eq!(
+            vec![
+                (PathBuf::from("prod"), vec![]),
+                (
+                    PathBuf::from("modules/warehouse"),
+                    vec!["warehouse".to_string()]
+                ),
+                (
+                    PathBuf::from("modules/warehouse/storage"),
+                    vec!["warehouse".to_string(), "storage".to_string()]
+                ),
+            ],
+            prod
+        );
+    }
+}
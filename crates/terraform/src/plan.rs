@@ -0,0 +1,458 @@
+use super::{BackingData, Error};
+use serde_json::{Map, Value as Json};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// A resource instance from the `planned_values` of `terraform show -json`, or from state
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Instance {
+    pub(crate) module_path: Vec<String>,
+    /// `managed` or `data`
+    pub(crate) mode: String,
+    pub(crate) block_type: String,
+    pub(crate) name: String,
+    /// The `0` or `"eu-west-1"` of resources using `count` or `for_each`
+    pub(crate) index: Option<String>,
+    /// e.g. `registry.terraform.io/hashicorp/aws`
+    pub(crate) provider: Option<String>,
+    pub(crate) values: Map<String, Json>,
+}
+
+/// Reads the JSON representation of a plan. Every resource instance in it becomes
+/// the block that would produce its planned values, so rules apply to them as they
+/// do to `.tf` files, but with the values only known at plan time filled in.
+/// Instances of every module are read, along with the `resource_changes` that
+/// `planned_values` leaves out.
+pub fn read_plan(path: PathBuf) -> Result<Vec<BackingData>, Error> {
+    let input = read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+    let plan: Json = serde_json::from_str(&input).map_err(|e| Error::json(&path, e))?;
+
+    let mut instances = Vec::new();
+    if let Some(root) = plan.pointer("/planned_values/root_module") {
+        collect(root, &mut instances);
+    }
+    if let Some(changes) = plan.get("resource_changes").and_then(Json::as_array) {
+        collect_changes(changes, &mut instances);
+    }
+
+    Ok(backing_data(instances, &path))
+}
+
+/// One `BackingData` per instance, reported as the file they were read from
+pub(crate) fn backing_data(instances: Vec<Instance>, path: &Path) -> Vec<BackingData> {
+    instances
+        .into_iter()
+        .map(|instance| {
+            let mut backing_data = super::parse_text(&render(&instance));
+            backing_data.path = path.to_string_lossy().into();
+            backing_data.module_path = instance.module_path;
+            backing_data.instance_key = instance.index;
+            backing_data
+        })
+        .collect()
+}
+
+fn collect(module: &Json, instances: &mut Vec<Instance>) {
+    let module_path = module
+        .get("address")
+        .and_then(Json::as_str)
+        .map(module_path)
+        .unwrap_or_default();
+
+    for resource in module
+        .get("resources")
+        .and_then(Json::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let text = |key: &str| resource.get(key).and_then(Json::as_str).map(String::from);
+        let (mode, block_type, name) = match (text("mode"), text("type"), text("name")) {
+            (Some(mode), Some(block_type), Some(name)) => (mode, block_type, name),
+            _ => continue,
+        };
+
+        instances.push(Instance {
+            module_path: module_path.clone(),
+            mode,
+            block_type,
+            name,
+            index: resource.get("index").map(Json::to_string),
+            provider: text("provider_name"),
+            values: resource
+                .get("values")
+                .and_then(Json::as_object)
+                .cloned()
+                .unwrap_or_default(),
+        });
+    }
+
+    for child in module
+        .get("child_modules")
+        .and_then(Json::as_array)
+        .into_iter()
+        .flatten()
+    {
+        collect(child, instances);
+    }
+}
+
+/// The instances of `resource_changes` that are still around after the plan is applied,
+/// with the values they will have. Instances already read from `planned_values` are skipped.
+fn collect_changes(changes: &[Json], instances: &mut Vec<Instance>) {
+    for change in changes {
+        let text = |key: &str| change.get(key).and_then(Json::as_str).map(String::from);
+        let (mode, block_type, name) = match (text("mode"), text("type"), text("name")) {
+            (Some(mode), Some(block_type), Some(name)) => (mode, block_type, name),
+            _ => continue,
+        };
+        // resources that get destroyed have no values afterwards
+        let values = match change.pointer("/change/after").and_then(Json::as_object) {
+            Some(values) => values.clone(),
+            None => continue,
+        };
+
+        let instance = Instance {
+            module_path: text("module_address")
+                .map(|address| module_path(&address))
+                .unwrap_or_default(),
+            mode,
+            block_type,
+            name,
+            index: change.get("index").map(Json::to_string),
+            provider: text("provider_name"),
+            values,
+        };
+        let known = instances.iter().any(|other| {
+            other.module_path == instance.module_path
+                && other.mode == instance.mode
+                && other.block_type == instance.block_type
+                && other.name == instance.name
+                && other.index == instance.index
+        });
+        if !known {
+            instances.push(instance);
+        }
+    }
+}
+
+/// `module.network.module.subnets[0]` is `["network", "subnets[0]"]`
+pub(crate) fn module_path(address: &str) -> Vec<String> {
+    address
+        .strip_prefix("module.")
+        .map(|rest| rest.split(".module.").map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// The block for an instance. Lists of objects become nested blocks, as that is
+/// how terraform shows them, everything else becomes an attribute.
+/// The provider shows up as the `provider` meta-argument.
+fn render(instance: &Instance) -> String {
+    let keyword = if instance.mode == "data" {
+        "data"
+    } else {
+        "resource"
+    };
+
+    let mut hcl = format!(
+        "{} \"{}\" \"{}\" {{\n",
+        keyword, instance.block_type, instance.name
+    );
+    if let Some(provider) = &instance.provider {
+        hcl.push_str(&format!("  provider = {}\n", quote(provider)));
+    }
+    body(&instance.values, 1, quote, &mut hcl);
+    hcl.push_str("}\n");
+    hcl
+}
+
+/// The attributes and nested blocks of a block, with strings written by `quote`
+pub(crate) fn body(
+    values: &Map<String, Json>,
+    depth: usize,
+    quote: fn(&str) -> String,
+    hcl: &mut String,
+) {
+    let indent = "  ".repeat(depth);
+    for (key, value) in values {
+        match value {
+            // `"//"` is how the JSON syntax writes comments
+            _ if key == "//" => continue,
+            Json::Null => continue,
+            // nested blocks can only have identifiers for names, other objects stay maps
+            Json::Array(items)
+                if is_identifier(key)
+                    && !items.is_empty()
+                    && items.iter().all(|item| {
+                        matches!(item, Json::Object(entries) if entries.keys().all(|k| is_identifier(k)))
+                    }) =>
+            {
+                for item in items.iter().filter_map(Json::as_object) {
+                    hcl.push_str(&format!("{}{} {{\n", indent, key));
+                    body(item, depth + 1, quote, hcl);
+                    hcl.push_str(&format!("{}}}\n", indent));
+                }
+            }
+            value => hcl.push_str(&format!(
+                "{}{} = {}\n",
+                indent,
+                name(key, quote),
+                expression(value, depth, quote)
+            )),
+        }
+    }
+}
+
+pub(crate) fn expression(value: &Json, depth: usize, quote: fn(&str) -> String) -> String {
+    match value {
+        Json::Null => "null".into(),
+        Json::Bool(b) => b.to_string(),
+        Json::Number(n) => n.to_string(),
+        Json::String(s) => quote(s),
+        Json::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(|item| expression(item, depth, quote))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Json::Object(entries) if entries.is_empty() => "{}".into(),
+        Json::Object(entries) => {
+            let indent = "  ".repeat(depth + 1);
+            let mut map = String::from("{\n");
+            for (key, value) in entries {
+                map.push_str(&format!(
+                    "{}{} = {}\n",
+                    indent,
+                    name(key, quote),
+                    expression(value, depth + 1, quote)
+                ));
+            }
+            map.push_str(&"  ".repeat(depth));
+            map.push('}');
+            map
+        }
+    }
+}
+
+/// A string literal, with `${` escaped so values from the plan are never templates
+fn quote(text: &str) -> String {
+    template(text).replace("${", "$${").replace("%{", "%%{")
+}
+
+/// A string literal that keeps the `${...}` in it, as in configuration files
+pub(crate) fn template(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Keys are written as they are, unless they are not an identifier
+fn name(key: &str, quote: fn(&str) -> String) -> String {
+    if is_identifier(key) {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn plan() -> Json {
+        serde_json::from_str(
+            r#"{
+  "format_version": "1.0",
+  "planned_values": {
+    "root_module": {
+      "resources": [
+        {
+          "address": "aws_instance.web[0]",
+          "mode": "managed",
+          "type": "aws_instance",
+          "name": "web",
+          "index": 0,
+          "provider_name": "registry.terraform.io/hashicorp/aws",
+          "values": {
+            "ami": "ami-123",
+            "tags": { "Name": "web", "aws:cloudformation": "x" },
+            "ebs_block_device": [{ "encrypted": true, "volume_size": 8 }],
+            "user_data": null
+          }
+        }
+      ],
+      "child_modules": [
+        {
+          "address": "module.warehouse",
+          "resources": [
+            {
+              "address": "module.warehouse.aws_db_instance.main",
+              "mode": "managed",
+              "type": "aws_db_instance",
+              "name": "main",
+              "values": { "engine": "postgres" }
+            }
+          ],
+          "child_modules": [
+            {
+              "address": "module.warehouse.module.backups",
+              "resources": [
+                {
+                  "address": "module.warehouse.module.backups.aws_s3_bucket.copies",
+                  "mode": "managed",
+                  "type": "aws_s3_bucket",
+                  "name": "copies",
+                  "values": { "bucket": "copies" }
+                }
+              ]
+            }
+          ]
+        }
+      ]
+    }
+  },
+  "resource_changes": [
+    {
+      "address": "module.warehouse.aws_db_instance.main",
+      "module_address": "module.warehouse",
+      "mode": "managed",
+      "type": "aws_db_instance",
+      "name": "main",
+      "change": { "actions": ["create"], "after": { "engine": "postgres" } }
+    },
+    {
+      "address": "module.network.aws_vpc.main",
+      "module_address": "module.network",
+      "mode": "managed",
+      "type": "aws_vpc",
+      "name": "main",
+      "provider_name": "registry.terraform.io/hashicorp/aws",
+      "change": { "actions": ["update"], "after": { "cidr_block": "10.0.0.0/16" } }
+    },
+    {
+      "address": "aws_instance.old",
+      "mode": "managed",
+      "type": "aws_instance",
+      "name": "old",
+      "change": { "actions": ["delete"], "after": null }
+    }
+  ]
+}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn collects_resources_of_every_module() {
+        let mut instances = Vec::new();
+        collect(
+            plan().pointer("/planned_values/root_module").unwrap(),
+            &mut instances,
+        );
+
+        let found: Vec<(Vec<String>, String, Option<String>)> = instances
+            .into_iter()
+            .map(|i| (i.module_path, i.name, i.index))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (vec![], "web".to_string(), Some("0".to_string())),
+                (vec!["warehouse".to_string()], "main".to_string(), None),
+                (
+                    vec!["warehouse".to_string(), "backups".to_string()],
+                    "copies".to_string(),
+                    None
+                ),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn adds_resource_changes_missing_from_the_planned_values() {
+        let plan = plan();
+        let mut instances = Vec::new();
+        collect(
+            plan.pointer("/planned_values/root_module").unwrap(),
+            &mut instances,
+        );
+        collect_changes(plan["resource_changes"].as_array().unwrap(), &mut instances);
+
+        let found: Vec<(Vec<String>, String)> = instances
+            .into_iter()
+            .map(|i| (i.module_path, i.name))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (vec![], "web".to_string()),
+                (vec!["warehouse".to_string()], "main".to_string()),
+                (
+                    vec!["warehouse".to_string(), "backups".to_string()],
+                    "copies".to_string()
+                ),
+                (vec!["network".to_string()], "main".to_string()),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn renders_instances_as_blocks() {
+        let mut instances = Vec::new();
+        collect(
+            plan().pointer("/planned_values/root_module").unwrap(),
+            &mut instances,
+        );
+
+        assert_eq!(
+            r#"resource "aws_instance" "web" {
+  provider = "registry.terraform.io/hashicorp/aws"
+  ami = "ami-123"
+  ebs_block_device {
+    encrypted = true
+    volume_size = 8
+  }
+  tags = {
+    Name = "web"
+    "aws:cloudformation" = "x"
+  }
+}
+"#,
+            render(&instances[0])
+        );
+    }
+
+    #[test]
+    fn objects_with_keys_that_arent_identifiers_stay_maps() {
+        let values = serde_json::from_str(r#"{"ingress": [{"aws:x": 1}]}"#).unwrap();
+        let mut hcl = String::new();
+        body(&values, 1, quote, &mut hcl);
+
+        assert_eq!("  ingress = [{\n    \"aws:x\" = 1\n  }]\n", hcl);
+    }
+
+    #[test]
+    fn templates_in_values_stay_literal() {
+        assert_eq!(r#""arn:$${aws:username}""#, quote("arn:${aws:username}"));
+    }
+}
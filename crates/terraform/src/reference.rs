@@ -0,0 +1,216 @@
+use super::BackingData;
+use tree_sitter::Node;
+
+/// What the first part of a reference points at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// `aws_db_instance.main.password`
+    Resource,
+    /// `data.aws_caller_identity.current.account_id`
+    Data,
+    /// `var.region`
+    Variable,
+    /// `local.tags`
+    Local,
+    /// `module.network.vpc_id`
+    Module,
+    /// `each.value`, `count.index`, `path.module`...
+    Other,
+}
+
+/// A reference taken apart, with indexes such as `[0]` dropped
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub target: Target,
+    /// The `aws_db_instance` of resources and data sources
+    pub block_type: Option<String>,
+    pub name: String,
+    pub attributes: Vec<String>,
+}
+
+/// Attributes providers mark as sensitive, by resource type
+const SENSITIVE_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("aws_db_instance", &["password"]),
+    ("aws_rds_cluster", &["master_password"]),
+    ("aws_iam_access_key", &["secret", "ses_smtp_password_v4"]),
+    ("aws_iam_user_login_profile", &["password"]),
+    (
+        "aws_secretsmanager_secret_version",
+        &["secret_string", "secret_binary"],
+    ),
+    ("aws_ssm_parameter", &["value"]),
+    ("random_password", &["result", "bcrypt_hash"]),
+    (
+        "tls_private_key",
+        &["private_key_pem", "private_key_openssh"],
+    ),
+];
+
+impl Reference {
+    pub fn parse(text: &str) -> Option<Reference> {
+        let mut parts = text.split('.').map(|part| match part.find('[') {
+            Some(idx) => &part[..idx],
+            None => part,
+        });
+        let first = parts.next()?;
+
+        let (target, block_type) = match first {
+            "data" => (Target::Data, Some(parts.next()?.to_string())),
+            "var" => (Target::Variable, None),
+            "local" => (Target::Local, None),
+            "module" => (Target::Module, None),
+            "each" | "count" | "path" | "self" | "terraform" => (Target::Other, None),
+            resource_type => (Target::Resource, Some(resource_type.to_string())),
+        };
+
+        Some(Reference {
+            target,
+            block_type,
+            name: parts.next()?.to_string(),
+            attributes: parts
+                .filter(|part| !part.is_empty())
+                .map(String::from)
+                .collect(),
+        })
+    }
+
+    /// Whether this reads an attribute the provider considers a secret
+    pub fn is_sensitive_attribute(&self) -> bool {
+        let (block_type, attribute) = match (&self.block_type, self.attributes.first()) {
+            (Some(block_type), Some(attribute)) => (block_type, attribute),
+            _ => return false,
+        };
+
+        SENSITIVE_ATTRIBUTES.iter().any(|(kind, attributes)| {
+            kind == block_type && attributes.contains(&attribute.as_str())
+        })
+    }
+}
+
+/// The references in an expression that lead to a secret: sensitive attributes
+/// of resources, sensitive variables and locals built from either of them.
+/// Variables and locals are looked up in the same file.
+pub fn sensitive_references(node: Node, terraform: &BackingData) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_sensitive(node, terraform, &mut Vec::new(), &mut found);
+    found
+}
+
+fn collect_sensitive(
+    node: Node,
+    terraform: &BackingData,
+    seen_locals: &mut Vec<String>,
+    found: &mut Vec<String>,
+) {
+    if node.kind() != "reference" {
+        for child in super::named_children(&node) {
+            collect_sensitive(child, terraform, seen_locals, found);
+        }
+        return;
+    }
+
+    let text = terraform.text(node);
+    let reference = match Reference::parse(text) {
+        Some(reference) => reference,
+        None => return,
+    };
+
+    match reference.target {
+        Target::Resource | Target::Data if reference.is_sensitive_attribute() => {
+            found.push(text.into())
+        }
+        Target::Variable if is_sensitive_variable(&reference.name, terraform) => {
+            found.push(text.into())
+        }
+        Target::Local if !seen_locals.contains(&reference.name) => {
+            seen_locals.push(reference.name.clone());
+            if let Some(value) = local(&reference.name, terraform) {
+                collect_sensitive(value, terraform, seen_locals, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_sensitive_variable(name: &str, terraform: &BackingData) -> bool {
+    super::top_level_blocks(terraform.root())
+        .into_iter()
+        .filter(|block| block.kind() == "variable")
+        .filter(|block| super::block_labels(block, &terraform.input) == [name])
+        .flat_map(|variable| super::named_children(&variable))
+        .flat_map(|body| super::named_children(&body))
+        .filter(|setting| setting.kind() == "sensitive")
+        .any(|sensitive| terraform.text(sensitive).ends_with("true"))
+}
+
+fn local<'a>(name: &str, terraform: &'a BackingData) -> Option<Node<'a>> {
+    super::top_level_blocks(terraform.root())
+        .into_iter()
+        .filter(|block| block.kind() == "locals")
+        .flat_map(|locals| super::named_children(&locals))
+        .find_map(|body| super::attribute(&body, name, &terraform.input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_references_to_resources() {
+        assert_eq!(
+            Some(Reference {
+                target: Target::Resource,
+                block_type: Some("aws_db_instance".into()),
+                name: "main".into(),
+                attributes: vec!["password".into()],
+            }),
+            Reference::parse("aws_db_instance.main[0].password")
+        );
+        assert_eq!(
+            Some(Target::Data),
+            Reference::parse("data.aws_iam_policy_document.admin.json").map(|r| r.target)
+        );
+    }
+
+    #[test]
+    fn knows_which_attributes_are_secrets() {
+        let password = Reference::parse("random_password.db.result").unwrap();
+        let length = Reference::parse("random_password.db.length").unwrap();
+
+        assert!(password.is_sensitive_attribute());
+        assert!(!length.is_sensitive_attribute());
+    }
+
+    #[test]
+    fn follows_locals_and_variables() {
+        let backing_data = crate::parse_text(
+            r#"
+variable "api_token" {
+  sensitive = true
+}
+
+locals {
+  credentials = "${aws_db_instance.main.username}:${aws_db_instance.main.password}"
+}
+
+output "connection" {
+  value = [local.credentials, var.api_token, var.region]
+}
+"#,
+        );
+
+        let output = crate::top_level_blocks(backing_data.root()).pop().unwrap();
+        let value = crate::attribute(
+            &crate::named_children(&output)[1],
+            "value",
+            &backing_data.input,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["aws_db_instance.main.password", "var.api_token"],
+            sensitive_references(value, &backing_data)
+        );
+    }
+}
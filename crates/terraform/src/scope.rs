@@ -0,0 +1,270 @@
+use super::evaluate::evaluate;
+use super::reference::{Reference, Target};
+use super::value::{unquote, Value};
+use super::{BackingData, Error};
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+/// Arguments of a `module` block that are not variables of the called module
+const META_ARGUMENTS: &[&str] = &[
+    "source",
+    "version",
+    "count",
+    "for_each",
+    "providers",
+    "depends_on",
+];
+
+/// The statically known values of `var.*` and `local.*` in a module.
+/// Anything we can't know before `terraform apply` stays a reference.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scope {
+    variables: BTreeMap<String, Value>,
+    locals: BTreeMap<String, Value>,
+}
+
+impl Scope {
+    /// Variables of a root module come from their `default` and the tfvars files,
+    /// where later files win over earlier ones
+    pub fn root(files: &[&BackingData], tfvars: &[BackingData]) -> Scope {
+        let mut variables = defaults(files);
+        for file in tfvars {
+            variables.extend(assignments(file, &Scope::default()));
+        }
+
+        Scope::with_locals(variables, files)
+    }
+
+    /// Variables of a called module come from the arguments of the `module "<name>"`
+    /// block in the caller, as far as the scope of the caller knows them
+    pub fn called(&self, name: &str, caller: &[&BackingData], files: &[&BackingData]) -> Scope {
+        let mut variables = defaults(files);
+        for file in caller {
+            let call = super::top_level_blocks(file.root())
+                .into_iter()
+                .filter(|block| block.kind() == "module")
+                .find(|block| super::block_labels(block, &file.input) == [name]);
+            let body = match call.and_then(|call| super::named_children(&call).pop()) {
+                Some(body) => body,
+                None => continue,
+            };
+
+            for (argument, value) in super::attributes(&body, &file.input) {
+                if !META_ARGUMENTS.contains(&argument.as_str()) {
+                    variables.insert(argument, evaluate(value, self, &file.input));
+                }
+            }
+        }
+
+        Scope::with_locals(variables, files)
+    }
+
+    /// Locals can refer to variables and to each other, so they get evaluated
+    /// until nothing changes anymore
+    fn with_locals(variables: BTreeMap<String, Value>, files: &[&BackingData]) -> Scope {
+        let mut scope = Scope {
+            variables,
+            locals: BTreeMap::new(),
+        };
+
+        // every round knows at least one more local, unless there is a cycle
+        for round in 0.. {
+            let evaluated: BTreeMap<String, Value> = files
+                .iter()
+                .flat_map(|file| assignments(file, &scope))
+                .collect();
+            if evaluated == scope.locals || round > evaluated.len() {
+                break;
+            }
+            scope.locals = evaluated;
+        }
+
+        scope
+    }
+
+    /// Replaces references to variables and locals by their values where known
+    pub fn resolve(&self, value: Value) -> Value {
+        match value {
+            Value::Reference(text) => self.lookup(&text).unwrap_or(Value::Reference(text)),
+            Value::List(items) => {
+                Value::List(items.into_iter().map(|item| self.resolve(item)).collect())
+            }
+            Value::Map(entries) => Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, self.resolve(value)))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    fn lookup(&self, text: &str) -> Option<Value> {
+        let reference = Reference::parse(text)?;
+        let value = match reference.target {
+            Target::Variable => self.variables.get(&reference.name)?,
+            Target::Local => self.locals.get(&reference.name)?,
+            _ => return None,
+        };
+
+        // `var.subnets[0].cidr` is `subnets` followed by `[0]` and `cidr`
+        let mut steps = text.split('.').skip(1).flat_map(|part| part.split('['));
+        steps.next();
+        steps
+            .try_fold(value, |value, step| match (value, step.strip_suffix(']')) {
+                (Value::List(items), Some(index)) => items.get(index.parse::<usize>().ok()?),
+                (Value::Map(entries), Some(key)) => entries.get(&unquote(key)),
+                (Value::Map(entries), None) => entries.get(step),
+                _ => None,
+            })
+            .cloned()
+    }
+}
+
+/// Reads a `.tfvars` file, which is the body of a block without the block
+pub fn read_tfvars(path: PathBuf) -> Result<BackingData, Error> {
+    let input = read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+    let mut backing_data = super::parse_text(&format!("locals {{\n{}\n}}\n", input));
+    backing_data.path = path.to_string_lossy().into();
+    Ok(backing_data)
+}
+
+/// The `default` of every variable
+fn defaults(files: &[&BackingData]) -> BTreeMap<String, Value> {
+    files
+        .iter()
+        .flat_map(|file| {
+            super::top_level_blocks(file.root())
+                .into_iter()
+                .filter(|block| block.kind() == "variable")
+                .filter_map(move |variable| {
+                    let name = super::block_labels(&variable, &file.input).pop()?;
+                    let default = super::named_children(&variable)
+                        .into_iter()
+                        .flat_map(|body| super::named_children(&body))
+                        .find(|setting| setting.kind() == "default")?;
+                    let value = super::named_children(&default).pop()?;
+
+                    Some((name, evaluate(value, &Scope::default(), &file.input)))
+                })
+        })
+        .collect()
+}
+
+/// The `name = value` pairs of `locals` blocks, which is also how tfvars get read
+fn assignments(file: &BackingData, scope: &Scope) -> BTreeMap<String, Value> {
+    super::top_level_blocks(file.root())
+        .into_iter()
+        .filter(|block| block.kind() == "locals")
+        .flat_map(|locals| super::named_children(&locals))
+        .flat_map(|body| super::attributes(&body, &file.input))
+        .map(|(name, value)| (name, evaluate(value, scope, &file.input)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn resolves_variables_and_locals() {
+        let module = crate::parse_text(
+            r#"
+variable "engine" {
+  default = "mysql"
+}
+
+variable "size" {}
+
+locals {
+  settings = { engine = local.engine }
+  engine   = var.engine
+}
+"#,
+        );
+        let tfvars = crate::parse_text(
+            r#"
+locals {
+  engine = "postgres"
+}
+"#,
+        );
+
+        let scope = Scope::root(&[&module], &[tfvars]);
+
+        assert_eq!(
+            Value::String("postgres".into()),
+            scope.resolve(Value::Reference("local.settings.engine".into()))
+        );
+        assert_eq!(
+            Value::Reference("var.size".into()),
+            scope.resolve(Value::Reference("var.size".into()))
+        );
+    }
+
+    #[test]
+    fn called_modules_get_their_variables_from_the_module_block() {
+        let caller = crate::parse_text(
+            r#"
+locals {
+  engine = "postgres"
+}
+
+module "warehouse" {
+  source = "./warehouse"
+  engine = local.engine
+}
+"#,
+        );
+        let warehouse = crate::parse_text(
+            r#"
+variable "engine" {
+  default = "mysql"
+}
+"#,
+        );
+
+        let root = Scope::root(&[&caller], &[]);
+        let scope = root.called("warehouse", &[&caller], &[&warehouse]);
+
+        assert_eq!(
+            Value::String("postgres".into()),
+            scope.resolve(Value::Reference("var.engine".into()))
+        );
+    }
+
+    #[test]
+    fn computes_locals_from_expressions() {
+        let module = crate::parse_text(
+            r#"
+variable "environment" {
+  default = "prod"
+}
+
+locals {
+  subnets    = ["10.0.1.0/24", "10.0.2.0/24"]
+  name       = "${var.environment}-db"
+  replicas   = var.environment == "prod" ? 3 : 1
+  identifier = upper(local.name)
+}
+"#,
+        );
+
+        let scope = Scope::root(&[&module], &[]);
+
+        assert_eq!(
+            Value::String("10.0.2.0/24".into()),
+            scope.resolve(Value::Reference("local.subnets[1]".into()))
+        );
+        assert_eq!(
+            Value::Number(3.0),
+            scope.resolve(Value::Reference("local.replicas".into()))
+        );
+        assert_eq!(
+            Value::String("PROD-DB".into()),
+            scope.resolve(Value::Reference("local.identifier".into()))
+        );
+    }
+}
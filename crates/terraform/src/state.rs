@@ -0,0 +1,116 @@
+use super::plan::{self, Instance};
+use super::{BackingData, Error};
+use serde_json::Value as Json;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+/// Reads a local state file in the version 4 format. Every resource instance
+/// in it becomes the block that would produce its recorded attributes, so rules
+/// apply to what is deployed the way they apply to `.tf` files.
+pub fn read_state(path: PathBuf) -> Result<Vec<BackingData>, Error> {
+    let input = read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+    let state: Json = serde_json::from_str(&input).map_err(|e| Error::json(&path, e))?;
+    match state.get("version").and_then(Json::as_u64) {
+        Some(4) => Ok(plan::backing_data(instances(&state), &path)),
+        version => Err(Error::UnsupportedState {
+            path: path.to_string_lossy().into(),
+            version,
+        }),
+    }
+}
+
+fn instances(state: &Json) -> Vec<Instance> {
+    let mut instances = Vec::new();
+    for resource in state
+        .get("resources")
+        .and_then(Json::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let text = |key: &str| resource.get(key).and_then(Json::as_str).map(String::from);
+        let (mode, block_type, name) = match (text("mode"), text("type"), text("name")) {
+            (Some(mode), Some(block_type), Some(name)) => (mode, block_type, name),
+            _ => continue,
+        };
+
+        for instance in resource
+            .get("instances")
+            .and_then(Json::as_array)
+            .into_iter()
+            .flatten()
+        {
+            instances.push(Instance {
+                module_path: text("module")
+                    .map(|module| plan::module_path(&module))
+                    .unwrap_or_default(),
+                mode: mode.clone(),
+                block_type: block_type.clone(),
+                name: name.clone(),
+                index: instance.get("index_key").map(Json::to_string),
+                provider: text("provider").map(|provider| provider_name(&provider)),
+                values: instance
+                    .get("attributes")
+                    .and_then(Json::as_object)
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+    }
+
+    instances
+}
+
+/// `provider["registry.terraform.io/hashicorp/aws"].west` is `registry.terraform.io/hashicorp/aws`,
+/// the way plans name the provider of a resource
+fn provider_name(provider: &str) -> String {
+    provider
+        .strip_prefix("provider[\"")
+        .and_then(|rest| rest.split('"').next())
+        .unwrap_or(provider)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn reads_every_instance_of_a_resource() {
+        let state: Json = serde_json::from_str(
+            r#"{
+  "version": 4,
+  "terraform_version": "1.3.0",
+  "resources": [
+    {
+      "module": "module.network",
+      "mode": "managed",
+      "type": "aws_subnet",
+      "name": "private",
+      "provider": "provider[\"registry.terraform.io/hashicorp/aws\"].west",
+      "instances": [
+        { "index_key": "a", "attributes": { "cidr_block": "10.0.1.0/24" } },
+        { "index_key": "b", "attributes": { "cidr_block": "10.0.2.0/24" } }
+      ]
+    }
+  ]
+}"#,
+        )
+        .unwrap();
+
+        let found: Vec<(Vec<String>, Option<String>, Option<String>)> = instances(&state)
+            .into_iter()
+            .map(|i| (i.module_path, i.index, i.provider))
+            .collect();
+
+        let network = vec!["network".to_string()];
+        let aws = Some("registry.terraform.io/hashicorp/aws".to_string());
+        assert_eq!(
+            vec![
+                (network.clone(), Some(r#""a""#.to_string()), aws.clone()),
+                (network, Some(r#""b""#.to_string()), aws),
+            ],
+            found
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::BTreeMap;
+use tree_sitter::Node;
+
+/// A structured view of a terraform expression.
+///
+/// Literals are decoded (quotes stripped, numbers parsed) so that
+/// documents written as `jsonencode({...})`, heredocs or plain maps
+/// can all be compared the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+    /// A reference such as `var.bucket` or `aws_s3_bucket.logs.arn`
+    Reference(String),
+    /// Anything we can't statically make sense of, kept as source text
+    Expression(String),
+}
+
+impl Value {
+    pub fn from_node(node: Node, source: &str) -> Value {
+        let text = node.utf8_text(source.as_bytes()).unwrap_or_default();
+
+        match node.kind() {
+            "number" => parse_number(text)
+                .map(Value::Number)
+                .unwrap_or_else(|| Value::Expression(text.into())),
+            "boolean" => Value::Bool(text == "true"),
+            "null" => Value::Null,
+            "string_literal" => Value::String(unquote(text)),
+            "identifier" | "query" => Value::String(text.into()),
+            "reference" => Value::Reference(text.into()),
+            "heredoc" => {
+                let body = heredoc_body(text);
+                Value::from_json(&body).unwrap_or(Value::String(body))
+            }
+            "value_or_query" | "fn_param" => match super::named_children(&node).first() {
+                Some(child) => Value::from_node(*child, source),
+                None => Value::Expression(text.into()),
+            },
+            "function" if super::function_name(&node) == Some("jsonencode") => {
+                match super::named_children(&node).first() {
+                    Some(param) => Value::from_node(*param, source),
+                    None => Value::Expression(text.into()),
+                }
+            }
+            "list" => {
+                let children = super::named_children(&node);
+                if children.iter().any(|c| c.kind() == "for_comprehension") {
+                    return Value::Expression(text.into());
+                }
+                Value::List(
+                    children
+                        .into_iter()
+                        .map(|c| Value::from_node(c, source))
+                        .collect(),
+                )
+            }
+            "interpolation_string" => match super::named_children(&node).as_slice() {
+                [substitution] if substitution.kind() == "interpolation_substitution" => {
+                    match super::named_children(substitution).as_slice() {
+                        [expression] => Value::from_node(*expression, source),
+                        _ => Value::Expression(text.into()),
+                    }
+                }
+                _ => Value::Expression(text.into()),
+            },
+            "map" => Value::Map(
+                super::named_children(&node)
+                    .into_iter()
+                    .filter_map(|entry| key_and_value(entry, source))
+                    .collect(),
+            ),
+            _ => Value::Expression(text.into()),
+        }
+    }
+
+    /// Compares values the way terraform would after converting types:
+    /// `"5"` is the same as `5`, `1.0` the same as `1` and `"true"` the same as `true`.
+    pub fn equivalent(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::String(s), Value::Number(n)) | (Value::Number(n), Value::String(s)) => {
+                parse_number(s.trim()) == Some(*n)
+            }
+            (Value::String(s), Value::Bool(b)) | (Value::Bool(b), Value::String(s)) => {
+                s == if *b { "true" } else { "false" }
+            }
+            (Value::List(a), Value::List(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.equivalent(b))
+            }
+            (Value::Map(a), Value::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(
+                        |(key, value)| matches!(b.get(key), Some(other) if value.equivalent(other)),
+                    )
+            }
+            (Value::Expression(a), Value::Expression(b)) => significant(a) == significant(b),
+            (a, b) => a == b,
+        }
+    }
+
+    /// Reads a JSON document, as found in heredocs and `.json` files.
+    pub fn from_json(text: &str) -> Option<Value> {
+        serde_json::from_str::<serde_json::Value>(text)
+            .ok()
+            .map(Value::from)
+    }
+
+    /// Writes the value as JSON.
+    /// References and expressions use terraform's own `"${...}"` notation.
+    pub fn to_json(&self) -> String {
+        serde_json::Value::from(self).to_string()
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+            serde_json::Value::String(s) => match template_reference(&s) {
+                Some(reference) => Value::Reference(reference.into()),
+                None => Value::String(s),
+            },
+            serde_json::Value::Array(items) => {
+                Value::List(items.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(entries) => Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, Value::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Number(n) => serde_json::Number::from_f64(*n)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            Value::String(s) => serde_json::Value::String(s.clone()),
+            Value::List(items) => {
+                serde_json::Value::Array(items.iter().map(serde_json::Value::from).collect())
+            }
+            Value::Map(entries) => serde_json::Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), serde_json::Value::from(v)))
+                    .collect(),
+            ),
+            Value::Reference(r) | Value::Expression(r) => {
+                serde_json::Value::String(format!("${{{}}}", r))
+            }
+        }
+    }
+}
+
+/// Parses the small literals that show up as arguments of placeholders,
+/// e.g. the `"gladis"` in `$("gladis" || "other")`.
+pub fn parse_literal(text: &str) -> Value {
+    let text = text.trim();
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "null" => Value::Null,
+        _ if text.starts_with('"') => Value::String(unquote(text)),
+        _ => parse_number(text)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(text.into())),
+    }
+}
+
+/// Strips the surrounding quotes of a string literal and resolves escapes
+pub fn unquote(text: &str) -> String {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+
+    // `$${` and `%%{` are how a literal `${` or `%{` is written
+    result.replace("$${", "${").replace("%%{", "%{")
+}
+
+/// An expression without the whitespace between its parts, what is inside of strings is kept
+fn significant(expression: &str) -> String {
+    let mut result = String::new();
+    let (mut in_string, mut escaped) = (false, false);
+    for c in expression.chars() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_whitespace() {
+            continue;
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok().map(|n| n as f64),
+        None => text.parse().ok(),
+    }
+}
+
+/// The lines between `<<EOF` and the closing `EOF`
+fn heredoc_body(text: &str) -> String {
+    let mut lines: Vec<&str> = text.lines().skip(1).collect();
+    lines.pop();
+    lines.join("\n")
+}
+
+fn key_and_value(entry: Node, source: &str) -> Option<(String, Value)> {
+    let children = super::named_children(&entry);
+    let key = children.first()?;
+    let value = children.last()?;
+    if key == value {
+        return None;
+    }
+
+    let key = match Value::from_node(*key, source) {
+        Value::String(s) => s,
+        other => other.to_json(),
+    };
+
+    Some((key, Value::from_node(*value, source)))
+}
+
+/// `"${var.foo}"` is how terraform's JSON syntax spells a reference
+fn template_reference(text: &str) -> Option<&str> {
+    let inner = text.strip_prefix("${")?.strip_suffix('}')?;
+    let is_reference = inner.starts_with(|c: char| c.is_ascii_alphabetic())
+        && inner
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.[]".contains(c));
+
+    if is_reference {
+        Some(inner)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn reads_json_documents() {
+        let value = Value::from_json(r#"{"Effect": "Allow", "Action": ["s3:*"], "Sid": 1}"#)
+            .expect("valid json");
+
+        let mut expected = BTreeMap::new();
+        expected.insert("Effect".to_string(), Value::String("Allow".into()));
+        expected.insert(
+            "Action".to_string(),
+            Value::List(vec![Value::String("s3:*".into())]),
+        );
+        expected.insert("Sid".to_string(), Value::Number(1.0));
+
+        assert_eq!(Value::Map(expected), value);
+    }
+
+    #[test]
+    fn references_round_trip_through_json() {
+        let value = Value::Reference("aws_s3_bucket.logs.arn".into());
+
+        assert_eq!(Some(value.clone()), Value::from_json(&value.to_json()));
+    }
+
+    #[test]
+    fn values_are_equivalent_after_type_conversion() {
+        assert!(Value::String("5".into()).equivalent(&Value::Number(5.0)));
+        assert!(Value::Number(1.0).equivalent(&parse_literal("1")));
+        assert!(Value::Bool(true).equivalent(&Value::String("true".into())));
+        assert!(!Value::String("yes".into()).equivalent(&Value::Bool(true)));
+        assert!(
+            Value::Expression("a  ==  b".into()).equivalent(&Value::Expression("a == b".into()))
+        );
+        assert!(!Value::Expression(r#"x == "a b""#.into())
+            .equivalent(&Value::Expression(r#"x == "ab""#.into())));
+        assert!(Value::Expression(r#"x == "a \" b""#.into())
+            .equivalent(&Value::Expression(r#"x=="a \" b""#.into())));
+    }
+
+    #[test]
+    fn unquotes_escaped_strings() {
+        assert_eq!(r#"a "quoted" word"#, unquote(r#""a \"quoted\" word""#));
+        assert_eq!("${aws:username}", unquote(r#""$${aws:username}""#));
+    }
+
+    #[test]
+    fn decodes_values_of_a_jsonencode_call() {
+        let backing_data = crate::parse_text(
+            r#"
+resource "aws_iam_policy" "admin" {
+  policy = jsonencode({
+    Statement = [{ Effect = "Allow", Action = "*" }]
+  })
+}
+"#,
+        );
+
+        let function = find(backing_data.root(), "function").expect("there was no function");
+        let value = Value::from_node(function, &backing_data.input);
+
+        assert_eq!(
+            Value::from_json(r#"{"Statement": [{"Effect": "Allow", "Action": "*"}]}"#),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn normalises_keys_and_single_substitutions() {
+        let backing_data = crate::parse_text(
+            r#"
+locals {
+  tags = {
+    Name    = "${var.name}"
+    "owner" = "data"
+  }
+}
+"#,
+        );
+
+        let map = find(backing_data.root(), "map").expect("there was no map");
+        let value = Value::from_node(map, &backing_data.input);
+
+        assert_eq!(
+            Value::from_json(r#"{"Name": "${var.name}", "owner": "data"}"#),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn decodes_json_heredocs() {
+        let backing_data = crate::parse_text(
+            r#"
+resource "aws_iam_policy" "admin" {
+  policy = <<EOF
+{
+  "Statement": [{"Effect": "Allow", "Action": "*"}]
+}
+EOF
+}
+"#,
+        );
+
+        let heredoc = find(backing_data.root(), "heredoc").expect("there was no heredoc");
+        let value = Value::from_node(heredoc, &backing_data.input);
+
+        assert_eq!(
+            Value::from_json(r#"{"Statement": [{"Effect": "Allow", "Action": "*"}]}"#),
+            Some(value)
+        );
+    }
+
+    fn find<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
+        if node.kind() == kind {
+            return Some(node);
+        }
+        crate::named_children(&node)
+            .into_iter()
+            .find_map(|child| find(child, kind))
+    }
+}
@@ -0,0 +1,97 @@
+use super::value::Value;
+
+/// `1.2.0` or `1.2.0-rc1`, but not ranges such as `~> 1.2`
+pub fn is_exact(version: &str) -> bool {
+    let release = version.split(['-', '+'].as_ref()).next().unwrap_or("");
+    let numbers: Vec<&str> = release.split('.').collect();
+
+    numbers.len() == 3
+        && numbers
+            .iter()
+            .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether a constraint such as `>= 3.0, < 4.0` stops at some version.
+/// `~> 3.2` only allows `3.x`, but `~> 3` allows anything from `3` onwards.
+pub fn has_upper_bound(constraint: &str) -> bool {
+    constraint.split(',').map(str::trim).any(|part| {
+        if let Some(version) = part.strip_prefix("~>") {
+            version.trim().contains('.')
+        } else if part.starts_with("!=") || part.starts_with('>') {
+            false
+        } else if part.starts_with('<') {
+            true
+        } else {
+            is_exact(part.strip_prefix('=').unwrap_or(part).trim())
+        }
+    })
+}
+
+/// The checks rule documents can use as placeholders for version constraints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Check {
+    /// `version = $(pinned_version)`: one exact version rather than a range
+    Pinned,
+    /// `version = $(version_upper_bound)`
+    UpperBound,
+}
+
+impl Check {
+    pub fn from_name(name: &str) -> Option<Check> {
+        match name {
+            "pinned_version" => Some(Check::Pinned),
+            "version_upper_bound" => Some(Check::UpperBound),
+            _ => None,
+        }
+    }
+
+    /// Besides a plain constraint, the value can be an entry of `required_providers`
+    /// such as `{ source = "hashicorp/aws", version = "~> 4.0" }` or the whole
+    /// `required_providers` block, in which case every provider has to pass.
+    pub fn passes(self, value: &Value) -> bool {
+        match value {
+            Value::String(constraint) => match self {
+                Check::Pinned => {
+                    let constraint = constraint.trim();
+                    is_exact(constraint.strip_prefix('=').unwrap_or(constraint).trim())
+                }
+                Check::UpperBound => has_upper_bound(constraint),
+            },
+            Value::Map(entries) => match entries.get("version") {
+                Some(version) => self.passes(version),
+                None => !entries.is_empty() && entries.values().all(|entry| self.passes(entry)),
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_upper_bounds_in_constraints() {
+        assert!(has_upper_bound("~> 4.2"));
+        assert!(has_upper_bound(">= 3.0, < 4.0"));
+        assert!(has_upper_bound("3.2.1"));
+        assert!(!has_upper_bound("~> 4"));
+        assert!(!has_upper_bound(">= 3.0, != 3.1.0"));
+    }
+
+    #[test]
+    fn every_required_provider_needs_to_pass() {
+        let providers = Value::from_json(
+            r#"{
+                "aws": {"source": "hashicorp/aws", "version": "~> 4.2"},
+                "random": {"source": "hashicorp/random", "version": ">= 3.0"}
+            }"#,
+        )
+        .unwrap();
+
+        assert!(!Check::UpperBound.passes(&providers));
+        assert!(Check::UpperBound.passes(&Value::String("< 2.0".into())));
+        assert!(Check::Pinned.passes(&Value::String("= 3.2.1".into())));
+        assert!(!Check::Pinned.passes(&Value::String("~> 3.2".into())));
+    }
+}
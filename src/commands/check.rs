@@ -1,9 +1,19 @@
 use argh::FromArgs;
 use glob::glob;
-use std::path::PathBuf;
+use std::error::Error;
+use std::path::{Path, PathBuf};
 
 use crate::Run;
-use report::{Report, StdoutReport};
+use document::conflict::{self, Precedence};
+use document::keyword::{Alias, Keywords};
+use document::rule::{MatchResult, Rule};
+use report::{Report, Severity, StdoutReport};
+use terraform::diagnostic::SyntaxErrors;
+use terraform::json_syntax;
+use terraform::module_tree::{self, ModuleTree};
+use terraform::plan;
+use terraform::scope::{self, Scope};
+use terraform::state;
 use terraform::BackingData;
 
 #[derive(FromArgs)]
@@ -12,6 +22,35 @@ use terraform::BackingData;
 pub struct Check {
     #[argh(positional)]
     path: PathBuf,
+
+    /// a tfvars file to read for the root modules, can be given several times
+    #[argh(option)]
+    var_file: Vec<PathBuf>,
+
+    /// the output of `terraform show -json` for a plan, checked instead of the .tf files
+    #[argh(option)]
+    plan: Option<PathBuf>,
+
+    /// a terraform.tfstate file, checked instead of the .tf files, not together with --plan
+    #[argh(option)]
+    state: Option<PathBuf>,
+
+    /// exit with a failure when a .tf file could not be parsed
+    #[argh(switch)]
+    fail_on_syntax_error: bool,
+
+    /// what to do with blocks one rule document allows and another denies:
+    /// report (the default), deny-overrides, allow-overrides or priority
+    #[argh(option, default = "Precedence::Report")]
+    precedence: Precedence,
+
+    /// another word rule headings can start with, e.g. `Forbid=Deny`, can be given several times
+    #[argh(option)]
+    keyword: Vec<Alias>,
+
+    /// the least severe result that fails the run: deny (the default), warn or info
+    #[argh(option, default = "Severity::Deny")]
+    fail_on: Severity,
 }
 
 fn paths_in(path: &str) -> Vec<PathBuf> {
@@ -21,31 +60,214 @@ fn paths_in(path: &str) -> Vec<PathBuf> {
         .collect()
 }
 
-impl Run for Check {
-    fn run(self) {
-        let mut report = StdoutReport::new(std::io::stdout());
-        let rule_paths = if self.path.is_dir() {
-            let pattern = format!("{}/*.md", self.path.to_string_lossy());
-            paths_in(&pattern)
-        } else {
-            vec![self.path]
-        };
+/// The markdown files in a directory of rules, or the one file given
+pub(crate) fn rule_paths(path: PathBuf) -> Vec<PathBuf> {
+    if path.is_dir() {
+        paths_in(&format!("{}/*.md", path.to_string_lossy()))
+    } else {
+        vec![path]
+    }
+}
+
+/// Everything that went wrong along the way, so one bad file doesn't stop
+/// the others from being checked. They are shown once the run is over.
+#[derive(Default)]
+struct Errors(Vec<Box<dyn Error>>);
+
+impl Errors {
+    fn keep<T, E: Error + 'static>(&mut self, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.0.push(Box::new(e));
+                None
+            }
+        }
+    }
+}
+
+/// The tfvars terraform would load on its own for a root module,
+/// followed by the ones passed with `--var-file`
+fn tfvars_for(directory: &Path, var_files: &[PathBuf], errors: &mut Errors) -> Vec<BackingData> {
+    let mut paths: Vec<PathBuf> = vec![directory.join("terraform.tfvars")];
+    paths.extend(paths_in(&directory.join("*.auto.tfvars").to_string_lossy()));
+    paths.extend(var_files.iter().cloned());
+
+    paths
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| errors.keep(scope::read_tfvars(path)))
+        .collect()
+}
+
+/// What every document has to say about a file, once the documents that
+/// disagree on a block have been settled
+fn check_file(
+    report: &mut dyn Report,
+    documents: &[document::Document],
+    terraform: &BackingData,
+    precedence: Precedence,
+    errors: &mut Errors,
+) {
+    let mut results: Vec<Vec<MatchResult>> =
+        documents.iter().map(|doc| doc.matches(terraform)).collect();
+    let conflicts = conflict::resolve(documents, &mut results, precedence);
+
+    for rule_matches in results {
+        errors.keep(report.about(terraform, rule_matches));
+    }
+    if !conflicts.is_empty() {
+        errors.keep(report.conflicts(terraform, &conflicts));
+    }
+}
 
-        let tf_files_to_check: Vec<BackingData> = paths_in("**/*.tf")
+/// Plans and state files have no modules to follow: every resource instance in them
+/// is checked on its own and `Require` rules have to be met by one of them.
+/// They only hold resources and data sources, so a `Require` of e.g. a `terraform {}`
+/// block is not applicable to them rather than missing.
+fn check_instances(
+    report: &mut dyn Report,
+    documents: &[document::Document],
+    instances: &[BackingData],
+    path: &Path,
+    precedence: Precedence,
+    errors: &mut Errors,
+) {
+    for instance in instances {
+        check_file(report, documents, instance, precedence, errors);
+    }
+
+    let files: Vec<&BackingData> = instances.iter().collect();
+    for doc in documents {
+        let missing: Vec<&Rule> = doc
+            .missing(&files)
             .into_iter()
-            .map(terraform::parse)
+            .filter(|rule| {
+                rule.targets
+                    .iter()
+                    .all(|target| matches!(target.kind.as_str(), "resource" | "data"))
+            })
             .collect();
+        if !missing.is_empty() {
+            errors.keep(report.missing(path, missing));
+        }
+    }
+}
+
+impl Run for Check {
+    fn run(self) {
+        if self.plan.is_some() && self.state.is_some() {
+            eprintln!("error: --plan and --state can't be checked together, pick one");
+            std::process::exit(1);
+        }
 
-        let all_document: Vec<document::Document> = rule_paths
+        let mut report = StdoutReport::new(std::io::stdout());
+        let mut errors = Errors::default();
+        let keywords = Keywords::with_aliases(&self.keyword);
+        let all_document: Vec<document::Document> = rule_paths(self.path)
             .into_iter()
-            .filter_map(document::from_path)
+            .filter_map(|path| errors.keep(document::from_path_with(path, &keywords)))
             .collect();
+        for warning in all_document.iter().flat_map(|doc| doc.warnings.iter()) {
+            eprintln!("warning: {}", warning);
+        }
+
+        let recorded = match (self.plan, self.state) {
+            (Some(path), _) => Some((plan::read_plan(path.clone()), path)),
+            (None, Some(path)) => Some((state::read_state(path.clone()), path)),
+            (None, None) => None,
+        };
+        let mut syntax_errors: Vec<SyntaxErrors> = Vec::new();
+        match recorded {
+            Some((instances, path)) => {
+                if let Some(instances) = errors.keep(instances) {
+                    check_instances(
+                        &mut report,
+                        &all_document,
+                        &instances,
+                        &path,
+                        self.precedence,
+                        &mut errors,
+                    );
+                }
+            }
+            None => {
+                let mut tf_files_to_check: Vec<BackingData> = Vec::new();
+                let parsed_files = paths_in("**/*.tf").into_iter().map(terraform::parse).chain(
+                    paths_in("**/*.tf.json")
+                        .into_iter()
+                        .map(json_syntax::parse_json),
+                );
+                for parsed in parsed_files {
+                    match parsed {
+                        // broken files are not checked at all, matching what could be parsed
+                        // of them would let a rule pass on whatever got lost
+                        Err(terraform::Error::Syntax(e)) => {
+                            errors.keep(report.syntax_errors(&e));
+                            syntax_errors.push(e);
+                        }
+                        other => tf_files_to_check.extend(errors.keep(other)),
+                    }
+                }
 
-        for doc in all_document {
-            for backing_data in tf_files_to_check.iter() {
-                let rule_matches = doc.matches(&backing_data);
+                check_modules(
+                    &mut report,
+                    &all_document,
+                    &tf_files_to_check,
+                    &self.var_file,
+                    self.precedence,
+                    &mut errors,
+                );
+            }
+        }
+
+        for e in &errors.0 {
+            eprintln!("error: {}", e);
+        }
+
+        let failed_on_syntax = self.fail_on_syntax_error && !syntax_errors.is_empty();
+        let failed_on_rules = report.severity() >= Some(self.fail_on);
+        if !errors.0.is_empty() || failed_on_syntax || failed_on_rules {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Every root module along with the modules it calls, once per call
+fn check_modules(
+    report: &mut dyn Report,
+    documents: &[document::Document],
+    files: &[BackingData],
+    var_files: &[PathBuf],
+    precedence: Precedence,
+    errors: &mut Errors,
+) {
+    let module_trees = module_tree::build(files);
+
+    let scopes: Vec<Vec<(&ModuleTree, Scope)>> = module_trees
+        .iter()
+        .map(|tree| {
+            let tfvars = tfvars_for(&tree.directory, var_files, errors);
+            tree.instances_with_scope(Scope::root(&tree.files, &tfvars))
+        })
+        .collect();
+
+    for (tree, instances) in module_trees.iter().zip(scopes.iter()) {
+        // modules called from several places are checked once per call
+        for (instance, scope) in instances.iter() {
+            for file in instance.files.iter() {
+                let mut backing_data = (*file).clone();
+                backing_data.module_path = instance.module_path.clone();
+                backing_data.scope = scope.clone();
+
+                check_file(report, documents, &backing_data, precedence, errors);
+            }
+        }
 
-                report.about(backing_data, rule_matches);
+        for doc in documents {
+            let missing = doc.missing(&tree.files);
+            if !missing.is_empty() {
+                errors.keep(report.missing(&tree.directory, missing));
             }
         }
     }
@@ -0,0 +1,47 @@
+use argh::FromArgs;
+use std::path::PathBuf;
+
+use super::check::rule_paths;
+use document::keyword::{Alias, Keywords};
+
+#[derive(FromArgs)]
+/// Looks for rules in the markdown files that don't do what they seem to
+#[argh(subcommand, name = "lint-rules")]
+pub struct LintRules {
+    #[argh(positional)]
+    path: PathBuf,
+
+    /// another word rule headings can start with, e.g. `Forbid=Deny`, can be given several times
+    #[argh(option)]
+    keyword: Vec<Alias>,
+}
+
+impl crate::Run for LintRules {
+    fn run(self) {
+        let keywords = Keywords::with_aliases(&self.keyword);
+        let mut failed = false;
+        for path in rule_paths(self.path) {
+            match document::from_path_with(path, &keywords) {
+                Ok(doc) => {
+                    for warning in &doc.warnings {
+                        println!("{}", warning);
+                        failed = true;
+                    }
+                    for finding in document::lint::lint(&doc) {
+                        println!("{}", finding);
+                        failed = true;
+                    }
+                }
+                // empty code blocks and snippets that don't parse are caught loading the document
+                Err(e) => {
+                    println!("{}", e);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            std::process::exit(1);
+        }
+    }
+}
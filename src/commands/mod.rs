@@ -1,5 +1,7 @@
 mod check;
+mod lint_rules;
 mod show;
 
 pub use check::Check;
+pub use lint_rules::LintRules;
 pub use show::Show;
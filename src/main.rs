@@ -2,6 +2,7 @@
 
 use argh::FromArgs;
 use commands::Check;
+use commands::LintRules;
 use commands::Show;
 
 mod commands;
@@ -22,12 +23,14 @@ pub trait Run {
 enum Subcommand {
     Show(Show),
     Check(Check),
+    LintRules(LintRules),
 }
 
 fn main() {
     match argh::from_env::<Args>().subcommand {
         Subcommand::Show(s) => s.run(),
         Subcommand::Check(c) => c.run(),
+        Subcommand::LintRules(l) => l.run(),
     }
 }
 
@@ -54,7 +57,7 @@ Some fancy reason why this matters
 
 ```
 resource "aws_rds_instance" $(*) {
-  size = $(somethings)
+  size = $(*)
 }
 ```
 
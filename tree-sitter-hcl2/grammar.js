@@ -54,7 +54,10 @@ const grammarObject = {
 
     variable: $ => seq(
       'variable',
-      alias($.string_literal, $.variable_name),
+      choice(
+        alias($.string_literal, $.variable_name),
+        $.query,
+      ),
       $.variable_block,
     ),
 
@@ -64,15 +67,18 @@ const grammarObject = {
 
     _typeOrDescriptionOrDefault: $ => choice(
       $.type,
-      $._description,
+      $.description,
       $.default,
+      $.sensitive,
     ),
 
-    _description: $ => seq("description", "=", alias($.string_literal, $.description)),
+    description: $ => seq("description", "=", choice($.string_literal, $.query)),
 
-    default: $ => seq("default", "=", $._expression),
+    default: $ => seq("default", "=", choice($._expression, $.query)),
 
-    type: $ => seq("type", "=", $._types),
+    type: $ => seq("type", "=", choice($._types, $.query)),
+
+    sensitive: $ => seq("sensitive", "=", choice($.boolean, $.query)),
 
     _types: $ => choice(
       $.list_ty,
@@ -99,11 +105,32 @@ const grammarObject = {
     tuple_ty: $ => seq('tuple', '(', '[', commaSep($._types), ']', ')'),
 
 
-    provider: $ => seq('provider', alias($.string_literal, $.provider_name), $.block),
+    provider: $ => seq(
+      'provider',
+      choice(
+        alias($.string_literal, $.provider_name),
+        $.query,
+      ),
+      $.block,
+    ),
 
-    output: $ => seq('output', alias($.string_literal, $.output_name), $.block),
+    output: $ => seq(
+      'output',
+      choice(
+        alias($.string_literal, $.output_name),
+        $.query,
+      ),
+      $.block,
+    ),
 
-    module: $ => seq('module', alias($.string_literal, $.module_name), $.block),
+    module: $ => seq(
+      'module',
+      choice(
+        alias($.string_literal, $.module_name),
+        $.query,
+      ),
+      $.block,
+    ),
 
     resource: $ => seq(
       'resource',
@@ -120,7 +147,10 @@ const grammarObject = {
     data: $ => seq(
       'data',
       alias($.string_literal, $.data_type),
-      alias($.string_literal, $.data_name),
+      choice(
+        alias($.string_literal, $.data_name),
+        $.query,
+      ),
       $.block,
     ),
 
@@ -147,6 +177,7 @@ const grammarObject = {
       $.map,
       $.reference,
       $.function,
+      $.heredoc,
     ),
 
     _literalValue: $ => choice(
@@ -217,6 +248,14 @@ const grammarObject = {
       "replace",
       "toset",
       "concat",
+      "jsonencode",
+      "format",
+      "join",
+      "lookup",
+      "lower",
+      "upper",
+      "tostring",
+      "cidrsubnet",
     ),
     "(", repeat(seq(alias($._expression, $.fn_param), optional(','))), ")"),
 
@@ -272,7 +311,7 @@ const grammarObject = {
 
     block: $ => seq(
       '{',
-      repeat($.attribute),
+      repeat(choice($.attribute, $.query)),
       '}',
     ),
 
@@ -308,7 +347,7 @@ const grammarObject = {
     interpolation_string: $ => seq(
       '"',
       repeat(choice(
-        $._template_chars,
+        $.template_chars,
         $.interpolation_substitution,
       )),
       '"',
@@ -325,7 +364,7 @@ const grammarObject = {
       $.sequence_expression,
     ),
 
-    _template_chars: $ => token(choice(repeat1(choice(
+    template_chars: $ => token(choice(repeat1(choice(
       /[^\\"$]/,
       /\$[^{"$]/,
       /\\(.|\n)/,
@@ -340,10 +379,24 @@ const grammarObject = {
       ),
     )),
 
+    // tree-sitter can't compare the closing marker with the opening one,
+    // so the first line made up of only an upper case word ends the heredoc.
+    heredoc: $ => token(seq(
+      /<<-?[A-Za-z_]+[ \t]*\n/,
+      repeat(/[ \t]*([^A-Z_ \t\n][^\n]*|[A-Z_]+[ \t]*[^A-Z_ \t\n][^\n]*)?\n/),
+      /[ \t]*[A-Z_]+/,
+    )),
+
+    // Strings with a `${...}` in them are interpolation_strings instead
     string_literal: $ => token(
       seq(
         '"',
-        repeat(/[^"]|(\\\")/),
+        repeat(choice(
+          /[^"\\$]/,
+          /\\(.|\n)/,
+          /\$[^{"]/,
+        )),
+        optional('$'),
         '"'
       )),
   },